@@ -1,8 +1,39 @@
 window.addEventListener("keyup", function(e) {
     var key = document.getElementById("key");
-    if (e.keyCode == 8 || e.keyCode == 13 || (e.keyCode >= 65 && e.keyCode <= 90)) {
+    // e.key is the character the player's own layout produced, so any hardware layout works
+    if (e.ctrlKey || e.altKey || e.metaKey) {
+        return;
+    }
+    if (e.key == "Backspace" || e.key == "Enter" || /^[a-z]$/i.test(e.key)) {
         e.preventDefault();
         key.value = e.key.toLowerCase();
         htmx.trigger("form", "submit");
     }
 });
+
+window.addEventListener("paste", function(e) {
+    var guess = e.clipboardData.getData("text");
+    htmx.ajax("POST", "/api/guess", { target: "#game", swap: "outerHTML", values: { guess: guess } });
+});
+
+document.addEventListener("click", function(e) {
+    if (!e.target.matches("button.copy") || !navigator.clipboard) {
+        return;
+    }
+    var text = document.getElementById(e.target.dataset.target);
+    navigator.clipboard.writeText(text.value).then(function() {
+        e.target.textContent = e.target.dataset.copied;
+    });
+});
+
+// htmx leaves error responses unswapped. Application errors are retargeted to the errors panel,
+// so show those, and clear the panel again once a request succeeds.
+document.addEventListener("htmx:beforeSwap", function(e) {
+    var errors = document.getElementById("errors");
+    if (e.detail.xhr.status < 400) {
+        errors.innerHTML = "";
+    } else if (e.detail.xhr.getResponseHeader("HX-Retarget") == "#errors") {
+        e.detail.shouldSwap = true;
+        e.detail.isError = false;
+    }
+});
@@ -0,0 +1,150 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+
+use crate::error::{AppError, JsonError};
+use crate::feedback::{self, Feedback};
+use crate::metrics;
+use crate::player::Stats;
+use crate::profile::Profile;
+use crate::state::{Error, GameState, Mode, Phase};
+use crate::{load_game, save_game, AppState, STATE_KEY};
+
+// Version 1 of the JSON API. Games are tied to the session cookie, one game per player.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/game", get(get_game).post(create_game))
+        .route("/game/guess", post(guess))
+        .route("/stats", get(stats))
+}
+
+#[derive(Serialize)]
+struct Tile {
+    letter: char,
+    result: Feedback,
+}
+
+#[derive(Serialize)]
+struct GuessView {
+    word: String,
+    tiles: Vec<Tile>,
+}
+
+#[derive(Serialize)]
+struct GameView {
+    mode: Mode,
+    hard: bool,
+    phase: Phase,
+    guesses: Vec<GuessView>,
+    remaining: usize,
+    // only revealed once the game is over
+    answer: Option<String>,
+}
+
+impl From<&GameState> for GameView {
+    fn from(state: &GameState) -> Self {
+        let guesses = state
+            .guesses
+            .iter()
+            .map(|guess| GuessView {
+                word: guess.to_string(),
+                tiles: guess
+                    .iter()
+                    .zip(feedback::score(*guess, state.answer))
+                    .map(|(letter, result)| Tile { letter, result })
+                    .collect(),
+            })
+            .collect();
+        Self {
+            mode: state.mode,
+            hard: state.hard,
+            phase: state.phase,
+            guesses,
+            remaining: 6 - state.guesses.len(),
+            answer: match state.phase {
+                Phase::Playing => None,
+                _ => Some(state.answer.to_string()),
+            },
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct CreateGame {
+    #[serde(default)]
+    mode: Mode,
+    // defaults to the player's setting
+    hard: Option<bool>,
+}
+
+async fn create_game(
+    State(app): State<AppState>,
+    session: Session,
+    mut profile: Profile,
+    params: Option<Json<CreateGame>>,
+) -> Result<(StatusCode, Json<GameView>), JsonError> {
+    let params = params.map(|Json(params)| params).unwrap_or_default();
+    if !app.config.enabled(params.mode) {
+        return Err(AppError::Invalid("error_mode_disabled").into());
+    }
+    let mut player = profile.load().await?;
+    let mut state = player.new_game(params.mode);
+    state.hard = params.hard.unwrap_or(state.hard);
+    metrics::game_started(&state);
+    session.insert(STATE_KEY, state.clone()).await?;
+    profile.save(&player).await?;
+    Ok((StatusCode::CREATED, Json(GameView::from(&state))))
+}
+
+const NO_GAME: AppError = AppError::NotFound("error_no_game");
+
+async fn get_game(session: Session) -> Result<Json<GameView>, JsonError> {
+    let state = load_game(&session).await?.ok_or(NO_GAME)?;
+    Ok(Json(GameView::from(&state)))
+}
+
+#[derive(Deserialize)]
+struct GuessParams {
+    guess: String,
+}
+
+async fn guess(
+    session: Session,
+    mut profile: Profile,
+    Json(params): Json<GuessParams>,
+) -> Result<Json<GameView>, JsonError> {
+    let mut state = load_game(&session).await?.ok_or(NO_GAME)?;
+    let before = state.clone();
+    if let Err(err) = state.guess(&params.guess) {
+        // rejected guesses are counted too, the saved game is left as it was
+        metrics::game_updated(&before, &state);
+        return Err(match err {
+            Error::GameOver => AppError::Conflict(err.key()),
+            err => AppError::Invalid(err.key()),
+        }
+        .into());
+    }
+
+    save_game(&session, &mut profile, &before, &state).await?;
+    Ok(Json(GameView::from(&state)))
+}
+
+#[derive(Serialize)]
+struct StatsView {
+    #[serde(flatten)]
+    stats: Stats,
+    win_percentage: u32,
+}
+
+async fn stats(profile: Profile) -> Result<Json<StatsView>, JsonError> {
+    let player = profile.load().await?;
+    Ok(Json(StatsView {
+        win_percentage: player.stats.win_percentage(),
+        stats: player.stats,
+    }))
+}
@@ -0,0 +1,155 @@
+use axum::{
+    extract::Path,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use lazy_static::lazy_static;
+use rust_embed::RustEmbed;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+// Everything in assets/ is compiled into the binary, including htmx and the fonts, so the
+// server works fully offline.
+#[derive(RustEmbed)]
+#[folder = "assets/"]
+struct Files;
+
+// Assets the page can't do without, checked at startup
+const REQUIRED: [&str; 3] = [
+    "htmx.min.js",
+    "fonts/Montserrat-Regular.ttf",
+    "fonts/Montserrat-Bold.ttf",
+];
+
+// Hashed names never change content, so they may be cached forever
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+// Plain names are always revalidated against the ETag
+const REVALIDATE: &str = "no-cache";
+
+struct Asset {
+    data: Cow<'static, [u8]>,
+    mime: String,
+    etag: String,
+}
+
+struct Assets {
+    // assets by their plain and hashed names
+    files: HashMap<String, Asset>,
+    // hashed names by plain name
+    hashed: HashMap<String, String>,
+}
+
+lazy_static! {
+    static ref ASSETS: Assets = Assets::load();
+}
+
+// Inserts the first bytes of the content hash before the extension, "style.css" -> "style.1a2b3c4d.css"
+fn hashed_name(name: &str, hash: &str) -> String {
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.ends_with('/') => format!("{}.{}.{}", stem, hash, ext),
+        _ => format!("{}.{}", name, hash),
+    }
+}
+
+impl Assets {
+    fn load() -> Self {
+        let mut files = HashMap::new();
+        let mut hashed = HashMap::new();
+        for name in Files::iter() {
+            let file = Files::get(&name).unwrap();
+            let hash: String = file.metadata.sha256_hash()[..6]
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect();
+            let asset = || Asset {
+                data: file.data.clone(),
+                mime: file.metadata.mimetype().to_string(),
+                etag: format!("\"{}\"", hash),
+            };
+            let hashed_name = hashed_name(&name, &hash);
+            files.insert(hashed_name.clone(), asset());
+            files.insert(name.to_string(), asset());
+            hashed.insert(name.to_string(), hashed_name);
+        }
+        Self { files, hashed }
+    }
+}
+
+// The url of an embedded asset, with its content hash in the file name
+pub fn url(name: &str) -> Option<String> {
+    ASSETS
+        .hashed
+        .get(name)
+        .map(|hashed| format!("/assets/{}", hashed))
+}
+
+// Fails with the required assets that were not in assets/ when the binary was built
+pub fn check() -> Result<(), String> {
+    let missing = REQUIRED
+        .into_iter()
+        .filter(|name| !ASSETS.hashed.contains_key(*name))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        return Err(format!(
+            "assets missing from the build: {}, see the README",
+            missing.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+pub async fn serve(Path(path): Path<String>, headers: HeaderMap) -> Response {
+    let Some(asset) = ASSETS.files.get(&path) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let cache = if ASSETS.hashed.contains_key(&path) {
+        REVALIDATE
+    } else {
+        IMMUTABLE
+    };
+    let cached = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(',').any(|tag| tag.trim() == asset.etag))
+        .unwrap_or(false);
+
+    let response_headers = [
+        (header::CONTENT_TYPE, asset.mime.clone()),
+        (header::ETAG, asset.etag.clone()),
+        (header::CACHE_CONTROL, cache.to_string()),
+    ];
+    if cached {
+        return (StatusCode::NOT_MODIFIED, response_headers).into_response();
+    }
+    (response_headers, asset.data.clone()).into_response()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hashed_name() {
+        assert_eq!(hashed_name("style.css", "abc"), "style.abc.css");
+        assert_eq!(
+            hashed_name("fonts/Montserrat-Regular.ttf", "abc"),
+            "fonts/Montserrat-Regular.abc.ttf"
+        );
+        assert_eq!(hashed_name("LICENSE", "abc"), "LICENSE.abc");
+    }
+
+    #[test]
+    fn test_url() {
+        let style = url("style.css").unwrap();
+        assert!(style.starts_with("/assets/style.") && style.ends_with(".css"));
+        assert!(ASSETS
+            .files
+            .contains_key(style.trim_start_matches("/assets/")));
+        assert_eq!(url("missing.css"), None);
+    }
+
+    #[test]
+    fn test_required() {
+        check().unwrap();
+    }
+}
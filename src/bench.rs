@@ -0,0 +1,67 @@
+use std::time::{Duration, Instant};
+
+use crate::dictionary::WORDS;
+use crate::solver;
+use crate::stats::WordFilter;
+use crate::word::Word;
+
+// Common openers, played before timing the positions they lead to
+const OPENERS: [&str; 3] = ["slate", "crane", "audio"];
+
+fn time<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+// Times candidate filtering, word by word and through the index, and solver rankings for
+// positions reached after common openers. Answers are spread evenly over the word list.
+pub fn run(rounds: usize) {
+    let answers: Vec<Word> = WORDS
+        .iter()
+        .step_by((WORDS.iter().len() / rounds.max(1)).max(1))
+        .take(rounds)
+        .copied()
+        .collect();
+
+    println!(
+        "{:<8} {:>10} {:>12} {:>12} {:>12}",
+        "opener", "choices", "linear", "index", "rank"
+    );
+    for opener in OPENERS {
+        let (mut linear, mut indexed, mut ranked) =
+            (Duration::ZERO, Duration::ZERO, Duration::ZERO);
+        let mut choices = 0;
+        for answer in &answers {
+            let mut filter = WordFilter::new(*answer);
+            filter.apply(Word::new(opener));
+
+            let (expected, elapsed) = time(|| {
+                WORDS
+                    .iter()
+                    .filter(|w| filter.matches(**w))
+                    .copied()
+                    .collect::<Vec<_>>()
+            });
+            linear += elapsed;
+
+            let (found, elapsed) = time(|| WORDS.filter(&filter));
+            indexed += elapsed;
+            assert_eq!(expected, found, "index disagrees with the filter");
+            choices += found.len();
+
+            let (_, elapsed) = time(|| solver::rank(&WORDS, &found, || false));
+            ranked += elapsed;
+        }
+
+        let n = answers.len().max(1) as u32;
+        println!(
+            "{:<8} {:>10} {:>12?} {:>12?} {:>12?}",
+            opener,
+            choices / n as usize,
+            linear / n,
+            indexed / n,
+            ranked / n
+        );
+    }
+}
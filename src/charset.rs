@@ -1,4 +1,4 @@
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Charset(u32);
 
 impl Charset {
@@ -35,6 +35,10 @@ impl Charset {
         self.clone()
     }
 
+    pub fn union(&self, other: Charset) -> Self {
+        Charset(self.0 | other.0)
+    }
+
     pub fn inverse(&self) -> Self {
         Charset(self.0 ^ Self::all().0)
     }
@@ -100,6 +104,12 @@ mod test {
         assert_eq!(Charset::none().inverse(), Charset::all());
     }
 
+    #[test]
+    fn test_charset_union() {
+        let set = Charset::from_str("ab").union(Charset::from_str("bc"));
+        assert_eq!(set, Charset::from_str("abc"));
+    }
+
     #[test]
     fn test_charset_contains_all() {
         let set = Charset::from_str("abc");
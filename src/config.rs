@@ -0,0 +1,441 @@
+use clap::{Parser, Subcommand};
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use tracing_subscriber::EnvFilter;
+
+use crate::dictionary::{self, Sources};
+use crate::limit::Limit;
+use crate::state::Mode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStoreKind {
+    Memory,
+    File,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+// Server configuration. Values come from command line flags, then environment variables,
+// then the optional TOML config file, and finally the defaults below.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub bind: String,
+    pub port: u16,
+    // seconds of inactivity before a session expires
+    pub session_expiry: u64,
+    // seconds a player record with stats and seen answers is kept after its last update
+    pub player_expiry: u64,
+    pub secure_cookies: bool,
+    pub session_store: SessionStoreKind,
+    pub session_dir: PathBuf,
+    // enables the admin endpoints
+    pub admin_token: Option<String>,
+    // word lists, the built in lists are used when unset
+    pub answers: Option<PathBuf>,
+    pub guesses: Option<PathBuf>,
+    // word frequencies, required by easy mode
+    pub frequencies: PathBuf,
+    pub blocklist: PathBuf,
+    pub allowlist: PathBuf,
+    // the first enabled mode is the default
+    pub modes: Vec<Mode>,
+    pub cheat: bool,
+    // a tracing filter, such as "info" or "wordlx=debug,tower_http=info"
+    pub log_level: String,
+    pub log_format: LogFormat,
+    // request rate limits for game input and for the solver
+    pub rate_limits: bool,
+    pub game_session_limit: Limit,
+    pub game_ip_limit: Limit,
+    pub cheat_session_limit: Limit,
+    pub cheat_ip_limit: Limit,
+    // rankings computed at the same time, and milliseconds allowed for each
+    pub solver_workers: usize,
+    pub solver_budget: u64,
+    // total number of ranked words kept in the solver cache, zero disables it
+    pub solver_cache: usize,
+    // what to do instead of serving, if anything
+    #[serde(skip)]
+    pub command: Option<Command>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0".to_string(),
+            port: 8080,
+            session_expiry: 3600,
+            player_expiry: 365 * 86400,
+            secure_cookies: false,
+            session_store: SessionStoreKind::Memory,
+            session_dir: "sessions".into(),
+            admin_token: None,
+            answers: None,
+            guesses: None,
+            frequencies: dictionary::FREQUENCY_FILE.into(),
+            blocklist: dictionary::BLOCKLIST_FILE.into(),
+            allowlist: dictionary::ALLOWLIST_FILE.into(),
+            modes: vec![Mode::Normal, Mode::Daily],
+            cheat: true,
+            log_level: "info".to_string(),
+            log_format: LogFormat::Text,
+            rate_limits: true,
+            game_session_limit: Limit::new(20.0, 40),
+            game_ip_limit: Limit::new(50.0, 100),
+            cheat_session_limit: Limit::new(0.2, 3),
+            cheat_ip_limit: Limit::new(1.0, 10),
+            solver_workers: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            solver_budget: 2000,
+            solver_cache: 500_000,
+            command: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Time candidate filtering and solver rankings, then exit
+    Bench {
+        /// Number of answers to time each opener against
+        #[arg(long, default_value_t = 100)]
+        rounds: usize,
+    },
+}
+
+#[derive(Debug, Parser)]
+#[command(version, about = "A Wordle clone")]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Path to a TOML config file
+    #[arg(short, long, env = "WORDLX_CONFIG")]
+    config: Option<PathBuf>,
+    /// Address to listen on
+    #[arg(long, env = "WORDLX_BIND")]
+    bind: Option<String>,
+    /// Port to listen on
+    #[arg(short, long, env = "WORDLX_PORT")]
+    port: Option<u16>,
+    /// Seconds of inactivity before a session expires
+    #[arg(long, env = "WORDLX_SESSION_EXPIRY")]
+    session_expiry: Option<u64>,
+    /// Seconds a player's stats and seen answers are kept after their last game
+    #[arg(long, env = "WORDLX_PLAYER_EXPIRY")]
+    player_expiry: Option<u64>,
+    /// Only send the session cookie over https
+    #[arg(long, env = "WORDLX_SECURE_COOKIES")]
+    secure_cookies: Option<bool>,
+    /// Where sessions are kept
+    #[arg(long, env = "WORDLX_SESSION_STORE")]
+    session_store: Option<SessionStoreKind>,
+    /// Directory of the file session store
+    #[arg(long, env = "WORDLX_SESSION_DIR")]
+    session_dir: Option<PathBuf>,
+    /// Bearer token for the admin endpoints
+    #[arg(long, env = "WORDLX_ADMIN_TOKEN")]
+    admin_token: Option<String>,
+    /// Answer word list, one word per line
+    #[arg(long, env = "WORDLX_ANSWERS")]
+    answers: Option<PathBuf>,
+    /// Valid guesses besides the answers, one word per line, instead of the built in list
+    #[arg(long, env = "WORDLX_GUESSES")]
+    guesses: Option<PathBuf>,
+    /// Word frequencies, one "word count" pair per line. Required by easy mode.
+    #[arg(long, env = "WORDLX_FREQUENCIES")]
+    frequencies: Option<PathBuf>,
+    /// Answers that are never picked
+    #[arg(long, env = "WORDLX_BLOCKLIST")]
+    blocklist: Option<PathBuf>,
+    /// Extra accepted guesses
+    #[arg(long, env = "WORDLX_ALLOWLIST")]
+    allowlist: Option<PathBuf>,
+    /// Enabled game modes, comma separated
+    #[arg(long, env = "WORDLX_MODES", value_delimiter = ',')]
+    modes: Option<Vec<Mode>>,
+    /// Enable the cheat solver
+    #[arg(long, env = "WORDLX_CHEAT")]
+    cheat: Option<bool>,
+    /// Log filter, such as "info" or "wordlx=debug"
+    #[arg(long, env = "WORDLX_LOG")]
+    log_level: Option<String>,
+    /// Log output format
+    #[arg(long, env = "WORDLX_LOG_FORMAT")]
+    log_format: Option<LogFormat>,
+    /// Enable request rate limits
+    #[arg(long, env = "WORDLX_RATE_LIMITS")]
+    rate_limits: Option<bool>,
+    /// Game input limit per session, as requests per second:burst
+    #[arg(long, env = "WORDLX_GAME_SESSION_LIMIT")]
+    game_session_limit: Option<Limit>,
+    /// Game input limit per client address, as requests per second:burst
+    #[arg(long, env = "WORDLX_GAME_IP_LIMIT")]
+    game_ip_limit: Option<Limit>,
+    /// Cheat limit per session, as requests per second:burst
+    #[arg(long, env = "WORDLX_CHEAT_SESSION_LIMIT")]
+    cheat_session_limit: Option<Limit>,
+    /// Cheat limit per client address, as requests per second:burst
+    #[arg(long, env = "WORDLX_CHEAT_IP_LIMIT")]
+    cheat_ip_limit: Option<Limit>,
+    /// Number of cheat rankings computed at the same time
+    #[arg(long, env = "WORDLX_SOLVER_WORKERS")]
+    solver_workers: Option<usize>,
+    /// Milliseconds a cheat ranking may take before the best partial ranking is returned
+    #[arg(long, env = "WORDLX_SOLVER_BUDGET")]
+    solver_budget: Option<u64>,
+    /// Total number of ranked words kept in the solver cache, 0 disables it
+    #[arg(long, env = "WORDLX_SOLVER_CACHE")]
+    solver_cache: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(String),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Read(path, err) => write!(f, "failed to read {}: {}", path.display(), err),
+            ConfigError::Parse(path, err) => {
+                write!(f, "invalid config {}: {}", path.display(), err)
+            }
+            ConfigError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    // Loads and validates the configuration from the command line, environment and config file
+    pub fn load() -> Result<Self, ConfigError> {
+        let args = Args::parse();
+        let mut config = match &args.config {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        config.apply(args);
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text =
+            std::fs::read_to_string(path).map_err(|err| ConfigError::Read(path.into(), err))?;
+        toml::from_str(&text).map_err(|err| ConfigError::Parse(path.into(), err))
+    }
+
+    fn apply(&mut self, args: Args) {
+        fn set<T>(value: &mut T, arg: Option<T>) {
+            if let Some(arg) = arg {
+                *value = arg;
+            }
+        }
+        set(&mut self.bind, args.bind);
+        set(&mut self.port, args.port);
+        set(&mut self.session_expiry, args.session_expiry);
+        set(&mut self.player_expiry, args.player_expiry);
+        set(&mut self.secure_cookies, args.secure_cookies);
+        set(&mut self.session_store, args.session_store);
+        set(&mut self.session_dir, args.session_dir);
+        set(&mut self.frequencies, args.frequencies);
+        set(&mut self.blocklist, args.blocklist);
+        set(&mut self.allowlist, args.allowlist);
+        set(&mut self.modes, args.modes);
+        set(&mut self.cheat, args.cheat);
+        set(&mut self.log_level, args.log_level);
+        set(&mut self.log_format, args.log_format);
+        set(&mut self.rate_limits, args.rate_limits);
+        set(&mut self.game_session_limit, args.game_session_limit);
+        set(&mut self.game_ip_limit, args.game_ip_limit);
+        set(&mut self.cheat_session_limit, args.cheat_session_limit);
+        set(&mut self.cheat_ip_limit, args.cheat_ip_limit);
+        set(&mut self.solver_workers, args.solver_workers);
+        set(&mut self.solver_budget, args.solver_budget);
+        set(&mut self.solver_cache, args.solver_cache);
+        self.command = args.command;
+        self.admin_token = args.admin_token.or(self.admin_token.take());
+        self.answers = args.answers.or(self.answers.take());
+        self.guesses = args.guesses.or(self.guesses.take());
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: String| Err(ConfigError::Invalid(message));
+        if self.bind.parse::<IpAddr>().is_err() {
+            return invalid(format!("bind address '{}' is not an ip address", self.bind));
+        }
+        if self.session_expiry == 0 || self.player_expiry == 0 {
+            return invalid("session and player expiry must be at least one second".to_string());
+        }
+        for path in [&self.answers, &self.guesses].into_iter().flatten() {
+            let list = std::fs::read_to_string(path)
+                .map_err(|err| ConfigError::Read(path.clone(), err))?;
+            if let Err(err) = dictionary::check_list(&list) {
+                return invalid(format!("invalid word list {}: {}", path.display(), err));
+            }
+        }
+        if self.modes.is_empty() {
+            return invalid("at least one game mode must be enabled".to_string());
+        }
+        // easy mode is normal mode in disguise without word frequencies
+        if self.enabled(Mode::Easy) && !self.frequencies.is_file() {
+            return invalid(format!(
+                "easy mode needs word frequencies, {} does not exist",
+                self.frequencies.display()
+            ));
+        }
+        if let Err(err) = self.log_level.parse::<EnvFilter>() {
+            return invalid(format!("invalid log level '{}': {}", self.log_level, err));
+        }
+        let limits = [
+            self.game_session_limit,
+            self.game_ip_limit,
+            self.cheat_session_limit,
+            self.cheat_ip_limit,
+        ];
+        if limits
+            .iter()
+            .any(|limit| !(limit.rate > 0.0 && limit.rate.is_finite()) || limit.burst == 0)
+        {
+            return invalid("rate limits must allow at least one request".to_string());
+        }
+        if self.solver_workers == 0 || self.solver_budget == 0 {
+            return invalid("the solver needs at least one worker and a time budget".to_string());
+        }
+        if self.admin_token.as_deref() == Some("") {
+            return invalid("admin token must not be empty".to_string());
+        }
+        Ok(())
+    }
+
+    // Installs the global tracing subscriber
+    pub fn init_logging(&self) {
+        let filter = EnvFilter::new(&self.log_level);
+        let logger = tracing_subscriber::fmt().with_env_filter(filter);
+        match self.log_format {
+            LogFormat::Text => logger.init(),
+            LogFormat::Json => logger.json().init(),
+        }
+    }
+
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.bind, self.port)
+    }
+
+    pub fn sources(&self) -> Sources {
+        Sources {
+            answers: self.answers.clone(),
+            guesses: self.guesses.clone(),
+            frequencies: self.frequencies.clone(),
+            blocklist: self.blocklist.clone(),
+            allowlist: self.allowlist.clone(),
+        }
+    }
+
+    pub fn enabled(&self, mode: Mode) -> bool {
+        self.modes.contains(&mode)
+    }
+
+    // The requested mode if it is enabled, otherwise the default mode
+    pub fn mode(&self, requested: Mode) -> Mode {
+        if self.enabled(requested) {
+            requested
+        } else {
+            self.modes[0]
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_config_file() {
+        let config: Config = toml::from_str(
+            r#"
+            port = 3000
+            modes = ["daily", "normal"]
+            cheat = false
+            session_store = "file"
+            cheat_ip_limit = { rate = 0.5, burst = 2 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.port, 3000);
+        assert_eq!(config.bind, "0.0.0.0");
+        assert_eq!(config.session_store, SessionStoreKind::File);
+        assert_eq!(config.cheat_ip_limit, Limit::new(0.5, 2));
+        assert!(!config.cheat);
+        assert!(!config.enabled(Mode::Easy));
+        assert_eq!(config.mode(Mode::Easy), Mode::Daily);
+        assert_eq!(config.mode(Mode::Normal), Mode::Normal);
+
+        assert!(toml::from_str::<Config>("prot = 3000").is_err());
+    }
+
+    #[test]
+    fn test_config_args() {
+        let mut config = Config::default();
+        let args = Args::parse_from(["wordlx", "--port", "9000", "--modes", "easy,daily"]);
+        config.apply(args);
+        assert_eq!(config.port, 9000);
+        assert_eq!(config.modes, vec![Mode::Easy, Mode::Daily]);
+        assert!(config.cheat);
+    }
+
+    #[test]
+    fn test_config_validate() {
+        let config = Config {
+            modes: vec![],
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = Config {
+            bind: "localhost".to_string(),
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+
+        let empty = std::env::temp_dir().join(format!("wordlx-empty-{}.txt", std::process::id()));
+        std::fs::write(&empty, "\n").unwrap();
+        let config = Config {
+            answers: Some(empty.clone()),
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+        let _ = std::fs::remove_file(&empty);
+
+        let config = Config {
+            log_level: "wordlx=loud".to_string(),
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+
+        for rate in [0.0, f64::NAN, f64::INFINITY] {
+            let config = Config {
+                game_ip_limit: Limit::new(rate, 10),
+                ..Config::default()
+            };
+            assert!(config.validate().is_err());
+        }
+
+        let config = Config {
+            modes: vec![Mode::Easy],
+            frequencies: "missing-freq.txt".into(),
+            ..Config::default()
+        };
+        assert!(config.validate().is_err());
+    }
+}
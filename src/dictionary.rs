@@ -1,54 +1,421 @@
 use lazy_static::lazy_static;
+use rand::distributions::{Distribution, WeightedIndex};
 use rand::Rng;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::index::{Bitset, WordIndex};
+use crate::query::Query;
+use crate::stats::WordFilter;
 use crate::word::Word;
 
+// Optional word frequencies, one "word count" pair per line
+pub const FREQUENCY_FILE: &str = "wordle-freq.txt";
+
+// Weight given to words without a known frequency
+const DEFAULT_WEIGHT: f64 = 1.0;
+
+// Operator maintained overlays on top of the built in lists, one word per line
+pub const BLOCKLIST_FILE: &str = "blocklist.txt";
+pub const ALLOWLIST_FILE: &str = "allowlist.txt";
+
+// Days between the unix epoch and the first Wordle
+const DAILY_EPOCH: u64 = 18797;
+
+// How many words the daily answer moves forward in the answer list from one day to the next,
+// wrapping around at the end. It is prime, so unless the list length is a multiple of it the
+// daily answers visit every word in the list before one repeats.
+const DAILY_STRIDE: u64 = 7919;
+
 lazy_static! {
     pub static ref WORDS: Dictionary = Dictionary::game_words();
     pub static ref GUESSES: Dictionary = Dictionary::valid_guesses();
 }
 
-pub struct Dictionary(HashSet<Word>);
+static SOURCES: OnceLock<Sources> = OnceLock::new();
+
+// Where the word lists are read from. Lists without a path use the built in ones.
+#[derive(Debug, Clone)]
+pub struct Sources {
+    pub answers: Option<PathBuf>,
+    pub guesses: Option<PathBuf>,
+    pub frequencies: PathBuf,
+    pub blocklist: PathBuf,
+    pub allowlist: PathBuf,
+}
+
+impl Default for Sources {
+    fn default() -> Self {
+        Self {
+            answers: None,
+            guesses: None,
+            frequencies: FREQUENCY_FILE.into(),
+            blocklist: BLOCKLIST_FILE.into(),
+            allowlist: ALLOWLIST_FILE.into(),
+        }
+    }
+}
+
+// Sets the word list sources. Must be called before the dictionaries are first used.
+pub fn configure(sources: Sources) {
+    SOURCES
+        .set(sources)
+        .expect("dictionary sources are already configured");
+}
+
+fn sources() -> &'static Sources {
+    SOURCES.get_or_init(Sources::default)
+}
+
+fn read_list(path: Option<&Path>, builtin: &str) -> String {
+    match path {
+        Some(path) => std::fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("failed to read {}: {}", path.display(), err)),
+        None => builtin.to_string(),
+    }
+}
+
+pub struct Dictionary {
+    words: Vec<Word>,
+    index: HashSet<Word>,
+    // bitsets over `words` for resolving constraints
+    lookup: WordIndex,
+    weights: HashMap<Word, f64>,
+    // words that are never picked as answers
+    blocked: RwLock<HashSet<Word>>,
+    // extra words accepted on top of the list
+    allowed: RwLock<HashSet<Word>>,
+}
 
 impl Dictionary {
     fn game_words() -> Self {
-        let words = include_str!("../wordle-valid.txt")
-            .lines()
-            .map(|s| Word::new(s))
-            .collect();
-        Self(words)
+        let sources = sources();
+        let list = read_list(
+            sources.answers.as_deref(),
+            include_str!("../wordle-valid.txt"),
+        );
+        let mut dict = Self::parse(&list);
+        if let Ok(frequencies) = std::fs::read_to_string(&sources.frequencies) {
+            dict.load_weights(&frequencies);
+        }
+        dict
     }
 
     fn valid_guesses() -> Self {
-        let words = include_str!("../wordle-guess.txt")
-            .lines()
-            .map(|s| Word::new(s))
-            .collect();
-        Self(words)
+        let list = read_list(
+            sources().guesses.as_deref(),
+            include_str!("../wordle-guess.txt"),
+        );
+        Self::parse(&list)
+    }
+
+    pub fn parse(list: &str) -> Self {
+        let words: Vec<Word> = list.lines().filter_map(parse_word).collect();
+        Self {
+            index: words.iter().copied().collect(),
+            lookup: WordIndex::new(&words),
+            words,
+            weights: HashMap::new(),
+            blocked: RwLock::new(HashSet::new()),
+            allowed: RwLock::new(HashSet::new()),
+        }
+    }
+
+    pub fn set_blocked(&self, words: HashSet<Word>) {
+        *self.blocked.write().unwrap() = words;
+    }
+
+    pub fn set_allowed(&self, words: HashSet<Word>) {
+        *self.allowed.write().unwrap() = words;
+    }
+
+    pub fn blocked(&self, word: Word) -> bool {
+        self.blocked.read().unwrap().contains(&word)
+    }
+
+    // Loads word frequencies from lines of "word count". Unknown words and malformed lines are ignored.
+    pub fn load_weights(&mut self, frequencies: &str) {
+        for line in frequencies.lines() {
+            let mut parts = line.split_whitespace();
+            let (Some(word), Some(count)) = (parts.next(), parts.next()) else {
+                continue;
+            };
+            let Ok(count) = count.parse::<f64>() else {
+                continue;
+            };
+            let word = Word::new(word);
+            if self.index.contains(&word) && count > 0.0 && count.is_finite() {
+                self.weights.insert(word, count);
+            }
+        }
+    }
+
+    pub fn weighted(&self) -> bool {
+        !self.weights.is_empty()
+    }
+
+    pub fn weight(&self, word: Word) -> f64 {
+        self.weights.get(&word).copied().unwrap_or(DEFAULT_WEIGHT)
     }
 
     pub fn contains(&self, word: Word) -> bool {
-        self.0.contains(&word)
+        self.index.contains(&word) || self.allowed.read().unwrap().contains(&word)
     }
 
-    pub fn iter(&self) -> std::collections::hash_set::Iter<Word> {
-        self.0.iter()
+    pub fn iter(&self) -> std::slice::Iter<'_, Word> {
+        self.words.iter()
+    }
+
+    // Positions of the words matching the filter
+    pub fn matching(&self, filter: &WordFilter) -> Bitset {
+        self.lookup
+            .matching(&filter.space, filter.required, filter.rejected)
+    }
+
+    pub fn words_in<'a>(&'a self, set: &'a Bitset) -> impl Iterator<Item = Word> + 'a {
+        set.iter().map(|i| self.words[i])
+    }
+
+    pub fn filter(&self, filter: &WordFilter) -> Vec<Word> {
+        self.words_in(&self.matching(filter)).collect()
+    }
+
+    pub fn query(&self, query: &Query) -> Vec<Word> {
+        // the index narrows down the candidates, letter counts are checked word by word
+        let set = self
+            .lookup
+            .matching(&query.space, query.required, query.excluded);
+        self.words_in(&set).filter(|w| query.matches(*w)).collect()
     }
 
     pub fn random(&self) -> Word {
+        self.pick(false, &HashSet::new()).unwrap()
+    }
+
+    // Picks a random word, biased towards common words if frequencies are loaded
+    pub fn random_common(&self) -> Word {
+        self.pick(true, &HashSet::new()).unwrap()
+    }
+
+    // Picks a random word that is neither blocked nor in the skip set, or None if every word is skipped
+    pub fn pick(&self, common: bool, skip: &HashSet<Word>) -> Option<Word> {
+        let blocked = self.blocked.read().unwrap();
+        let words = self
+            .words
+            .iter()
+            .filter(|w| !skip.contains(w) && !blocked.contains(w))
+            .copied()
+            .collect::<Vec<_>>();
+        if words.is_empty() {
+            return None;
+        }
+
         let mut rng = rand::thread_rng();
-        rng.gen_range(0..self.0.len());
-        let index = rng.gen_range(0..self.0.len());
-        self.0.iter().nth(index).unwrap().clone()
+        if !common || !self.weighted() {
+            return Some(words[rng.gen_range(0..words.len())]);
+        }
+        let weights = words.iter().map(|w| self.weight(*w)).collect::<Vec<_>>();
+        // weights that sum to zero or overflow can't be sampled, pick uniformly instead
+        let total = weights.iter().sum::<f64>();
+        if !(total > 0.0 && total.is_finite()) {
+            return Some(words[rng.gen_range(0..words.len())]);
+        }
+        let dist = WeightedIndex::new(weights).expect("weights are positive and finite");
+        Some(words[dist.sample(&mut rng)])
+    }
+
+    // The answer of the given day, shared by all players
+    pub fn daily(&self, day: u32) -> Word {
+        let len = self.words.len() as u64;
+        let start = day as u64 * DAILY_STRIDE;
+        // blocked answers are skipped by moving on to the next word in the list
+        (0..len)
+            .map(|offset| self.words[((start + offset) % len) as usize])
+            .find(|w| !self.blocked(*w))
+            .unwrap_or(self.words[(start % len) as usize])
+    }
+}
+
+// Reads a word list overlay. A missing file is treated as an empty list.
+fn read_overlay(path: &Path) -> std::io::Result<HashSet<Word>> {
+    let list = match std::fs::read_to_string(path) {
+        Ok(list) => list,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(err) => return Err(err),
+    };
+    Ok(parse_overlay(&list))
+}
+
+// Reads the word on a line of a word list in any case, or None if it is not a five letter word
+fn parse_word(line: &str) -> Option<Word> {
+    let line = line.trim().to_lowercase();
+    let valid = line.len() == 5 && line.chars().all(|c| c.is_ascii_lowercase());
+    valid.then(|| Word::new(&line))
+}
+
+// Checks that a word list has a five letter word on every line that is not blank.
+// Returns the number of words.
+pub fn check_list(list: &str) -> Result<usize, String> {
+    let mut count = 0;
+    for (n, line) in list.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if parse_word(line).is_none() {
+            return Err(format!(
+                "line {} '{}' is not a five letter word",
+                n + 1,
+                line.trim()
+            ));
+        }
+        count += 1;
+    }
+    if count == 0 {
+        return Err("the list has no words".to_string());
     }
+    Ok(count)
+}
+
+// Parses one word per line, skipping blank lines, # comments and anything that is not a five letter word
+fn parse_overlay(list: &str) -> HashSet<Word> {
+    list.lines().filter_map(parse_word).collect()
+}
+
+// Applies the blocklist to the answers and the allowlist to the guesses.
+// Returns the number of blocked and allowed words.
+pub fn load_overlays() -> std::io::Result<(usize, usize)> {
+    let blocked = read_overlay(&sources().blocklist)?;
+    let allowed = read_overlay(&sources().allowlist)?;
+    if WORDS.iter().all(|w| blocked.contains(w)) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "the blocklist blocks every answer",
+        ));
+    }
+    let counts = (blocked.len(), allowed.len());
+    WORDS.set_blocked(blocked);
+    GUESSES.set_allowed(allowed);
+    Ok(counts)
+}
+
+// Number of days since the first Wordle, 2021-06-19
+pub fn today() -> u32 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    (now.as_secs() / 86400).saturating_sub(DAILY_EPOCH) as u32
+}
+
+// Formats a day number as a calendar date, YYYY-MM-DD
+pub fn date(day: u32) -> String {
+    // days since 0000-03-01, so leap days fall at the end of each year
+    let days = day as i64 + DAILY_EPOCH as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", y, m, d)
 }
 
 impl IntoIterator for Dictionary {
     type Item = Word;
-    type IntoIter = std::collections::hash_set::IntoIter<Word>;
+    type IntoIter = std::vec::IntoIter<Word>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.words.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dictionary_weights() {
+        let mut dict = Dictionary::parse("about\nabove\nabuse");
+        assert!(!dict.weighted());
+        assert_eq!(dict.weight(Word::new("about")), DEFAULT_WEIGHT);
+
+        dict.load_weights("about 1000\nabove 20\nzebra 5\nabuse oops\n");
+        assert!(dict.weighted());
+        assert_eq!(dict.weight(Word::new("about")), 1000.0);
+        assert_eq!(dict.weight(Word::new("above")), 20.0);
+        assert_eq!(dict.weight(Word::new("abuse")), DEFAULT_WEIGHT);
+        assert!(!dict.contains(Word::new("zebra")));
+    }
+
+    #[test]
+    fn test_dictionary_pick() {
+        let dict = Dictionary::parse("about\nabove\nabuse");
+        let mut skip = HashSet::new();
+        skip.insert(Word::new("about"));
+        skip.insert(Word::new("abuse"));
+        assert_eq!(dict.pick(false, &skip), Some(Word::new("above")));
+        assert_eq!(dict.pick(true, &skip), Some(Word::new("above")));
+        skip.insert(Word::new("above"));
+        assert_eq!(dict.pick(false, &skip), None);
+    }
+
+    #[test]
+    fn test_dictionary_overlays() {
+        let dict = Dictionary::parse("about\nabove\nabuse");
+        let list = parse_overlay("# offensive\nabove\n\n  ABUSE \nnope\nzebra\n");
+        assert_eq!(list.len(), 3);
+
+        dict.set_blocked(list.clone());
+        assert_eq!(dict.random(), Word::new("about"));
+        assert_eq!(dict.daily(3), Word::new("about"));
+
+        assert!(!dict.contains(Word::new("zebra")));
+        dict.set_allowed(list);
+        assert!(dict.contains(Word::new("zebra")));
+    }
+
+    #[test]
+    fn test_check_list() {
+        assert_eq!(check_list("about\n\nABOVE\n  abuse \n"), Ok(3));
+        assert!(check_list("").is_err());
+        assert!(check_list("\n  \n").is_err());
+        assert!(check_list("about\nabout2\n").is_err());
+        assert!(check_list("about\nabs\n").is_err());
+
+        let dict = Dictionary::parse("About\nABOVE\n");
+        assert!(dict.contains(Word::new("about")));
+        assert!(dict.contains(Word::new("above")));
+    }
+
+    #[test]
+    fn test_dictionary_daily() {
+        let dict = Dictionary::parse("about\nabove\nabuse");
+        assert_eq!(dict.daily(10), dict.daily(10));
+        assert_ne!(dict.daily(10), dict.daily(11));
+    }
+
+    #[test]
+    fn test_date() {
+        assert_eq!(date(0), "2021-06-19");
+        assert_eq!(date(1), "2021-06-20");
+        assert_eq!(date(985), "2024-02-29");
+        assert_eq!(date(1947), "2026-10-18");
+    }
+
+    #[test]
+    fn test_dictionary_random_common() {
+        let mut dict = Dictionary::parse("about\nabove");
+        dict.load_weights("about 1\nabove 0.000001");
+        for _ in 0..10 {
+            assert!(dict.contains(dict.random_common()));
+        }
+
+        // weights too large to sum fall back to a uniform pick
+        let mut dict = Dictionary::parse("about\nabove");
+        dict.load_weights("about 1e308\nabove 1e308\nabove inf");
+        assert!(dict.contains(dict.random_common()));
     }
 }
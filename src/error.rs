@@ -0,0 +1,152 @@
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use maud::html;
+use std::time::Duration;
+use tower_sessions::session;
+use tracing::error;
+
+use crate::i18n::Locale;
+use crate::profile::Profile;
+
+// Errors a handler can fail with. Page handlers return them as they are, which renders
+// an HTML fragment, while the JSON API wraps them in `JsonError`. Messages are keys in
+// the message catalogs.
+#[derive(Debug)]
+pub enum AppError {
+    // the session could not be loaded from or saved to the store
+    Session(session::Error),
+    NotFound(&'static str),
+    Conflict(&'static str),
+    Invalid(&'static str),
+    // over a rate limit, with how long until the next request is allowed
+    TooManyRequests(Duration),
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Session(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    // The catalog key of what the client is told. Internal errors are only logged, never shown.
+    pub fn key(&self) -> &'static str {
+        match self {
+            AppError::Session(_) => "error_session",
+            AppError::TooManyRequests(_) => "error_rate_limited",
+            AppError::NotFound(key) | AppError::Conflict(key) | AppError::Invalid(key) => key,
+        }
+    }
+
+    fn log(&self) {
+        if let AppError::Session(err) = self {
+            error!(%err, "session failed");
+        }
+    }
+
+    // The error as an HTML fragment or a JSON body
+    fn respond(&self, json: bool) -> Response {
+        self.log();
+        let message = Message {
+            key: self.key(),
+            json,
+        };
+        let mut response = message.respond(self.status());
+        if let AppError::TooManyRequests(retry) = self {
+            let seconds = retry.as_secs_f64().ceil().max(1.0) as u64;
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, seconds.into());
+        }
+        response
+    }
+}
+
+impl From<session::Error> for AppError {
+    fn from(err: session::Error) -> Self {
+        AppError::Session(err)
+    }
+}
+
+// The message of an error response, kept on the response so `localize` can render it again
+// in the language of the request
+#[derive(Debug, Clone, Copy)]
+struct Message {
+    key: &'static str,
+    json: bool,
+}
+
+impl Message {
+    fn render(&self, lang: Locale) -> Response {
+        let message = lang.text(self.key);
+        if self.json {
+            return Json(serde_json::json!({ "error": message })).into_response();
+        }
+        html! { p.message.error { (message) } }.into_response()
+    }
+
+    fn respond(self, status: StatusCode) -> Response {
+        let mut response = (status, self.render(Locale::default())).into_response();
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+// Renders the message into the #errors panel of the page. htmx does not swap error
+// responses by default, wordle.js lets through the ones that are retargeted like this.
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let headers = [("HX-Retarget", "#errors"), ("HX-Reswap", "innerHTML")];
+        (headers, self.respond(false)).into_response()
+    }
+}
+
+// An application error as a JSON body, {"error": "..."}
+#[derive(Debug)]
+pub struct JsonError(pub AppError);
+
+impl From<AppError> for JsonError {
+    fn from(err: AppError) -> Self {
+        JsonError(err)
+    }
+}
+
+impl From<session::Error> for JsonError {
+    fn from(err: session::Error) -> Self {
+        JsonError(err.into())
+    }
+}
+
+impl IntoResponse for JsonError {
+    fn into_response(self) -> Response {
+        self.0.respond(true)
+    }
+}
+
+// Middleware translating the messages of error responses. Errors are turned into responses
+// without knowing the request, so they are rendered in English first and replaced here.
+// The language is only looked up for error responses, which spares the others a store read.
+pub async fn localize(
+    profile: Option<Profile>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    if let Some(message) = response.extensions_mut().remove::<Message>() {
+        let lang = Locale::resolve(profile.as_ref(), &headers).await;
+        // the length was set for the English message
+        response.headers_mut().remove(header::CONTENT_LENGTH);
+        *response.body_mut() = message.render(lang).into_body();
+    }
+    response
+}
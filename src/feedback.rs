@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+
+use crate::word::Word;
+
+// Ordered from least to most informative
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Feedback {
+    Absent,
+    Present,
+    Correct,
+}
+
+// Scores a guess against the answer the way Wordle does. Exact matches are marked first,
+// then repeated letters are only marked present as many times as they remain in the answer.
+pub fn score(guess: Word, answer: Word) -> [Feedback; 5] {
+    let mut result = [Feedback::Absent; 5];
+    let mut remaining = [0usize; 26];
+
+    for (i, tile) in result.iter_mut().enumerate() {
+        let (g, a) = (guess.at(i), answer.at(i));
+        if g == a {
+            *tile = Feedback::Correct;
+        } else if a.is_ascii_lowercase() {
+            remaining[a as usize - 'a' as usize] += 1;
+        }
+    }
+
+    for (i, tile) in result.iter_mut().enumerate() {
+        let g = guess.at(i);
+        if *tile == Feedback::Correct || !g.is_ascii_lowercase() {
+            continue;
+        }
+        let count = &mut remaining[g as usize - 'a' as usize];
+        if *count > 0 {
+            *count -= 1;
+            *tile = Feedback::Present;
+        }
+    }
+
+    result
+}
+
+impl Feedback {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Feedback::Absent => "absent",
+            Feedback::Present => "present",
+            Feedback::Correct => "correct",
+        }
+    }
+}
+
+// The best feedback each letter a to z has received over the guesses, or None if it was never guessed
+pub fn letter_states(guesses: &[Word], answer: Word) -> [Option<Feedback>; 26] {
+    let mut states = [None; 26];
+    for guess in guesses {
+        for (c, result) in guess.iter().zip(score(*guess, answer)) {
+            if !c.is_ascii_lowercase() {
+                continue;
+            }
+            let state = &mut states[c as usize - 'a' as usize];
+            *state = (*state).max(Some(result));
+        }
+    }
+    states
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use Feedback::*;
+
+    #[test]
+    fn test_score() {
+        let result = score(Word::new("crane"), Word::new("crane"));
+        assert_eq!(result, [Correct; 5]);
+
+        let result = score(Word::new("react"), Word::new("crane"));
+        assert_eq!(result, [Present, Present, Correct, Present, Absent]);
+    }
+
+    #[test]
+    fn test_score_repeated_letters() {
+        // only one e in the answer, and it is matched exactly
+        let result = score(Word::new("geese"), Word::new("those"));
+        assert_eq!(result, [Absent, Absent, Absent, Correct, Correct]);
+
+        // both l's are in the answer, in other positions
+        let result = score(Word::new("llama"), Word::new("hello"));
+        assert_eq!(result, [Present, Present, Absent, Absent, Absent]);
+
+        // exact matches use up the l's before the first one is considered
+        let result = score(Word::new("lolly"), Word::new("hello"));
+        assert_eq!(result, [Absent, Present, Correct, Correct, Absent]);
+    }
+
+    #[test]
+    fn test_letter_states() {
+        let guesses = [Word::new("react"), Word::new("cramp")];
+        let states = letter_states(&guesses, Word::new("crane"));
+        let state = |c: char| states[c as usize - 'a' as usize];
+        // present in the first guess, correct in the second
+        assert_eq!(state('c'), Some(Correct));
+        assert_eq!(state('e'), Some(Present));
+        assert_eq!(state('t'), Some(Absent));
+        assert_eq!(state('z'), None);
+    }
+}
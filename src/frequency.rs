@@ -0,0 +1,132 @@
+use serde::Serialize;
+
+use crate::word::Word;
+
+pub const LETTERS: usize = 26;
+
+fn index(c: char) -> Option<usize> {
+    if c.is_ascii_lowercase() {
+        Some(c as usize - 'a' as usize)
+    } else {
+        None
+    }
+}
+
+pub fn letter(i: usize) -> char {
+    (b'a' + i as u8) as char
+}
+
+// Letter statistics over a set of words. All tables are indexed by letter, a through z.
+#[derive(Debug, Clone, Serialize)]
+pub struct LetterStats {
+    pub words: usize,
+    // total number of occurrences of each letter
+    pub overall: [usize; LETTERS],
+    // occurrences of each letter at each of the five positions
+    pub positional: [[usize; LETTERS]; 5],
+    // occurrences of each adjacent letter pair, first letter major
+    pub bigrams: [[usize; LETTERS]; LETTERS],
+    // number of words containing each letter at least once
+    pub coverage: [usize; LETTERS],
+}
+
+impl LetterStats {
+    pub fn new<'a>(words: impl IntoIterator<Item = &'a Word>) -> Self {
+        let mut stats = Self {
+            words: 0,
+            overall: [0; LETTERS],
+            positional: [[0; LETTERS]; 5],
+            bigrams: [[0; LETTERS]; LETTERS],
+            coverage: [0; LETTERS],
+        };
+        for word in words {
+            stats.add(*word);
+        }
+        stats
+    }
+
+    fn add(&mut self, word: Word) {
+        self.words += 1;
+        let mut previous: Option<usize> = None;
+        for (i, c) in word.iter().enumerate() {
+            let Some(c) = index(c) else {
+                previous = None;
+                continue;
+            };
+            self.overall[c] += 1;
+            self.positional[i][c] += 1;
+            if let Some(p) = previous {
+                self.bigrams[p][c] += 1;
+            }
+            previous = Some(c);
+        }
+        let set = word.charset();
+        for i in 0..LETTERS {
+            if set.includes(letter(i)) {
+                self.coverage[i] += 1;
+            }
+        }
+    }
+
+    // Fraction of words containing the letter
+    pub fn coverage_score(&self, c: char) -> f64 {
+        match index(c) {
+            Some(i) if self.words > 0 => self.coverage[i] as f64 / self.words as f64,
+            _ => 0.0,
+        }
+    }
+
+    // Expected information, in bits, from learning whether the letter is in the word.
+    // Letters found in about half of the words split them best.
+    pub fn information(&self, c: char) -> f64 {
+        let p = self.coverage_score(c);
+        if p <= 0.0 || p >= 1.0 {
+            return 0.0;
+        }
+        -(p * p.log2() + (1.0 - p) * (1.0 - p).log2())
+    }
+
+    // Letters that still tell us something, most informative first
+    pub fn informative(&self) -> Vec<(char, f64)> {
+        let mut letters = (0..LETTERS)
+            .map(|i| (letter(i), self.information(letter(i))))
+            .filter(|(_, info)| *info > 0.0)
+            .collect::<Vec<_>>();
+        letters.sort_by(|a, b| b.1.total_cmp(&a.1));
+        letters
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_letter_stats() {
+        let words = [Word::new("hello"), Word::new("world")];
+        let stats = LetterStats::new(&words);
+        assert_eq!(stats.words, 2);
+        assert_eq!(stats.overall[index('l').unwrap()], 3);
+        assert_eq!(stats.overall[index('o').unwrap()], 2);
+        assert_eq!(stats.positional[0][index('h').unwrap()], 1);
+        assert_eq!(stats.positional[3][index('l').unwrap()], 2);
+        assert_eq!(stats.bigrams[index('l').unwrap()][index('l').unwrap()], 1);
+        assert_eq!(stats.bigrams[index('o').unwrap()][index('r').unwrap()], 1);
+        assert_eq!(stats.coverage[index('l').unwrap()], 2);
+        assert_eq!(stats.coverage[index('h').unwrap()], 1);
+    }
+
+    #[test]
+    fn test_letter_information() {
+        let words = [Word::new("hello"), Word::new("world")];
+        let stats = LetterStats::new(&words);
+        assert_eq!(stats.coverage_score('l'), 1.0);
+        assert_eq!(stats.information('l'), 0.0);
+        assert_eq!(stats.information('z'), 0.0);
+        assert_eq!(stats.information('h'), 1.0);
+
+        let informative = stats.informative();
+        assert!(informative.iter().all(|(c, _)| *c != 'l' && *c != 'o'));
+        assert!(informative.iter().any(|(c, _)| *c == 'w'));
+    }
+}
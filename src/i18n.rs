@@ -0,0 +1,231 @@
+use async_trait::async_trait;
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap},
+};
+use lazy_static::lazy_static;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt::Display;
+
+use crate::profile::Profile;
+use crate::AppState;
+
+// Languages of the user interface, with a message catalog for each in locales/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    Sv,
+    De,
+}
+
+type Catalog = HashMap<String, String>;
+
+lazy_static! {
+    static ref CATALOGS: HashMap<Locale, Catalog> = [
+        (Locale::En, include_str!("../locales/en.toml")),
+        (Locale::Sv, include_str!("../locales/sv.toml")),
+        (Locale::De, include_str!("../locales/de.toml")),
+    ]
+    .into_iter()
+    .map(|(locale, source)| {
+        let catalog = toml::from_str(source)
+            .unwrap_or_else(|err| panic!("invalid {} catalog: {}", locale.as_str(), err));
+        (locale, catalog)
+    })
+    .collect();
+}
+
+impl Locale {
+    pub const ALL: [Locale; 3] = [Locale::En, Locale::Sv, Locale::De];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Sv => "sv",
+            Locale::De => "de",
+        }
+    }
+
+    // The name of the language in the language itself
+    pub fn name(&self) -> &'static str {
+        match self {
+            Locale::En => "English",
+            Locale::Sv => "Svenska",
+            Locale::De => "Deutsch",
+        }
+    }
+
+    // Matches a language tag such as "sv" or "de-AT" by its primary language
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let language = tag.split(['-', '_']).next()?.trim();
+        Locale::ALL
+            .into_iter()
+            .find(|locale| locale.as_str().eq_ignore_ascii_case(language))
+    }
+
+    // Picks the supported language the client prefers most from an Accept-Language header,
+    // e.g. "sv-SE,sv;q=0.9,en;q=0.8". Falls back to English.
+    pub fn negotiate(accept: &str) -> Locale {
+        let mut languages = accept
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+                Some((tag, quality))
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect::<Vec<_>>();
+        // stable, so equally preferred languages keep their order
+        languages.sort_by(|a, b| b.1.total_cmp(&a.1));
+        languages
+            .into_iter()
+            .find_map(|(tag, _)| Locale::from_tag(tag))
+            .unwrap_or_default()
+    }
+
+    // The message for a key, from English if the catalog lacks it, or the key itself
+    pub fn text(&self, key: &'static str) -> &'static str {
+        CATALOGS[self]
+            .get(key)
+            .or_else(|| CATALOGS[&Locale::En].get(key))
+            .map_or(key, |text| text.as_str())
+    }
+
+    // The message for a key with each {name} replaced by its value
+    pub fn format(&self, key: &'static str, args: &[(&str, &dyn Display)]) -> String {
+        args.iter()
+            .fold(self.text(key).to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{}}}", name), &value.to_string())
+            })
+    }
+
+    // A message about a number of things with {count} replaced, using `one` for a single one
+    pub fn plural(&self, one: &'static str, other: &'static str, count: usize) -> String {
+        let key = if count == 1 { one } else { other };
+        self.format(key, &[("count", &count)])
+    }
+}
+
+// Reads a language override from a form or the session, where empty means none
+pub fn deserialize_override<'de, D>(deserializer: D) -> Result<Option<Locale>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)?.as_deref() {
+        None | Some("") => Ok(None),
+        Some(tag) => Locale::from_tag(tag)
+            .map(Some)
+            .ok_or_else(|| de::Error::custom(format!("unknown language {}", tag))),
+    }
+}
+
+// The language of a request: the player's own choice if they made one, otherwise
+// the best match for the browser's Accept-Language header
+#[async_trait]
+impl FromRequestParts<AppState> for Locale {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        app: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let profile = Profile::from_request_parts(parts, app).await.ok();
+        Ok(Locale::resolve(profile.as_ref(), &parts.headers).await)
+    }
+}
+
+impl Locale {
+    // The language of a request with the given player and headers
+    pub async fn resolve(profile: Option<&Profile>, headers: &HeaderMap) -> Locale {
+        if let Some(profile) = profile {
+            let player = profile.load().await.ok();
+            if let Some(locale) = player.and_then(|player| player.settings.locale) {
+                return locale;
+            }
+        }
+        let accept = headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("");
+        Locale::negotiate(accept)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(Locale::negotiate(""), Locale::En);
+        assert_eq!(Locale::negotiate("sv-SE,sv;q=0.9,en;q=0.8"), Locale::Sv);
+        assert_eq!(
+            Locale::negotiate("fr-FR,fr;q=0.9,de;q=0.8,en;q=0.7"),
+            Locale::De
+        );
+        assert_eq!(Locale::negotiate("en;q=0.5, de"), Locale::De);
+        assert_eq!(Locale::negotiate("sv;q=0, fr"), Locale::En);
+        assert_eq!(Locale::negotiate("*"), Locale::En);
+    }
+
+    #[test]
+    fn test_plural() {
+        assert_eq!(Locale::En.plural("choices_one", "choices", 1), "1 choice");
+        assert_eq!(Locale::En.plural("choices_one", "choices", 2), "2 choices");
+        assert_eq!(
+            Locale::De.plural("word_count_one", "word_count", 1),
+            "1 Wort"
+        );
+        assert_eq!(
+            Locale::De.plural("word_count_one", "word_count", 0),
+            "0 Wörter"
+        );
+    }
+
+    #[test]
+    fn test_catalogs() {
+        let placeholders = |text: &str| {
+            let mut names = text
+                .split('{')
+                .skip(1)
+                .filter_map(|part| part.split_once('}').map(|(name, _)| name.to_string()))
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+        let english = &CATALOGS[&Locale::En];
+        for locale in Locale::ALL {
+            let catalog = &CATALOGS[&locale];
+            for (key, text) in english {
+                let translated = catalog
+                    .get(key)
+                    .unwrap_or_else(|| panic!("{} is missing {}", locale.as_str(), key));
+                assert_eq!(placeholders(text), placeholders(translated), "{}", key);
+            }
+            for key in catalog.keys() {
+                assert!(
+                    english.contains_key(key),
+                    "{} has unknown {}",
+                    locale.as_str(),
+                    key
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            Locale::Sv.format("answer_was", &[("answer", &"CRANE")]),
+            "Svaret var CRANE"
+        );
+        assert_eq!(Locale::De.text("won"), "Gewonnen!");
+    }
+}
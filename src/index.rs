@@ -0,0 +1,166 @@
+use crate::charset::Charset;
+use crate::stats::WordSpace;
+use crate::word::Word;
+
+// A set of word positions in a dictionary, one bit per word
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitset {
+    blocks: Vec<u64>,
+    len: usize,
+}
+
+impl Bitset {
+    pub fn empty(len: usize) -> Self {
+        Self {
+            blocks: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    pub fn full(len: usize) -> Self {
+        let mut set = Self {
+            blocks: vec![u64::MAX; len.div_ceil(64)],
+            len,
+        };
+        // keep the bits past the end clear, they are not words
+        if !len.is_multiple_of(64) {
+            *set.blocks.last_mut().unwrap() = (1 << (len % 64)) - 1;
+        }
+        set
+    }
+
+    pub fn insert(&mut self, i: usize) {
+        self.blocks[i / 64] |= 1 << (i % 64);
+    }
+
+    pub fn and(&mut self, other: &Bitset) {
+        for (a, b) in self.blocks.iter_mut().zip(&other.blocks) {
+            *a &= b;
+        }
+    }
+
+    pub fn and_not(&mut self, other: &Bitset) {
+        for (a, b) in self.blocks.iter_mut().zip(&other.blocks) {
+            *a &= !b;
+        }
+    }
+
+    // Positions in the set, in increasing order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.blocks.iter().enumerate().flat_map(|(i, block)| {
+            let mut block = *block;
+            std::iter::from_fn(move || {
+                if block == 0 {
+                    return None;
+                }
+                let bit = block.trailing_zeros() as usize;
+                block &= block - 1;
+                Some(i * 64 + bit)
+            })
+        })
+    }
+}
+
+fn letter_index(c: char) -> usize {
+    c as usize - 'a' as usize
+}
+
+// An inverted index over a word list, with a bitset of words for every letter in every position
+// and for every letter anywhere in the word. Constraints on letters then resolve to the matching
+// words with a handful of AND and AND NOT operations over the whole list at once.
+#[derive(Debug)]
+pub struct WordIndex {
+    len: usize,
+    // words with letter c in position i, at i * 26 + c
+    at: Vec<Bitset>,
+    // words containing letter c
+    has: Vec<Bitset>,
+}
+
+impl WordIndex {
+    pub fn new(words: &[Word]) -> Self {
+        let len = words.len();
+        let mut at = vec![Bitset::empty(len); 5 * 26];
+        let mut has = vec![Bitset::empty(len); 26];
+        for (n, word) in words.iter().enumerate() {
+            for (i, c) in word.iter().enumerate() {
+                if !c.is_ascii_lowercase() {
+                    continue;
+                }
+                at[i * 26 + letter_index(c)].insert(n);
+                has[letter_index(c)].insert(n);
+            }
+        }
+        Self { len, at, has }
+    }
+
+    pub fn all(&self) -> Bitset {
+        Bitset::full(self.len)
+    }
+
+    // Words allowed by the space that contain every required letter and none of the excluded ones
+    pub fn matching(&self, space: &WordSpace, required: Charset, excluded: Charset) -> Bitset {
+        let mut set = self.all();
+        for c in 'a'..='z' {
+            let letter = letter_index(c);
+            if required.includes(c) {
+                set.and(&self.has[letter]);
+            }
+            if excluded.includes(c) {
+                set.and_not(&self.has[letter]);
+                continue;
+            }
+            for i in 0..5 {
+                if !space.at(i).includes(c) {
+                    set.and_not(&self.at[i * 26 + letter]);
+                }
+            }
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bitset() {
+        let mut set = Bitset::empty(130);
+        set.insert(3);
+        set.insert(64);
+        set.insert(129);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![3, 64, 129]);
+
+        let mut full = Bitset::full(130);
+        assert_eq!(full.iter().count(), 130);
+        full.and_not(&set);
+        assert_eq!(full.iter().count(), 127);
+        full.and(&set);
+        assert_eq!(full.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_index_matching() {
+        let words = ["crane", "crank", "drink", "slate", "abbey"].map(Word::new);
+        let index = WordIndex::new(&words);
+        let matching = |space: &WordSpace, required: &str, excluded: &str| {
+            let set = index.matching(
+                space,
+                Charset::from_str(required),
+                Charset::from_str(excluded),
+            );
+            set.iter().map(|i| words[i].to_string()).collect::<Vec<_>>()
+        };
+
+        let space = WordSpace::new();
+        assert_eq!(matching(&space, "", "").len(), 5);
+        assert_eq!(matching(&space, "k", ""), vec!["crank", "drink"]);
+        assert_eq!(matching(&space, "", "e"), vec!["crank", "drink"]);
+
+        let mut space = WordSpace::new();
+        space.only(0, 'c');
+        space.exclude(4, 'k');
+        assert_eq!(matching(&space, "", ""), vec!["crane"]);
+    }
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+// On-screen keyboard layouts. Only the letter keys are laid out, enter and backspace
+// always go on either side of the bottom row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    #[default]
+    Qwerty,
+    Azerty,
+    Qwertz,
+    Dvorak,
+    Colemak,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 5] = [
+        Layout::Qwerty,
+        Layout::Azerty,
+        Layout::Qwertz,
+        Layout::Dvorak,
+        Layout::Colemak,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Layout::Qwerty => "qwerty",
+            Layout::Azerty => "azerty",
+            Layout::Qwertz => "qwertz",
+            Layout::Dvorak => "dvorak",
+            Layout::Colemak => "colemak",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layout::Qwerty => "QWERTY",
+            Layout::Azerty => "AZERTY",
+            Layout::Qwertz => "QWERTZ",
+            Layout::Dvorak => "Dvorak",
+            Layout::Colemak => "Colemak",
+        }
+    }
+
+    pub fn rows(&self) -> [&'static str; 3] {
+        match self {
+            Layout::Qwerty => ["qwertyuiop", "asdfghjkl", "zxcvbnm"],
+            Layout::Azerty => ["azertyuiop", "qsdfghjklm", "wxcvbn"],
+            Layout::Qwertz => ["qwertzuiop", "asdfghjkl", "yxcvbnm"],
+            Layout::Dvorak => ["pyfgcrl", "aoeuidhtns", "qjkxbmwvz"],
+            Layout::Colemak => ["qwfpgjluy", "arstdhneio", "zxcvbkm"],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_layouts_have_every_letter() {
+        for layout in Layout::ALL {
+            let mut letters: Vec<char> = layout.rows().concat().chars().collect();
+            letters.sort();
+            assert_eq!(
+                letters,
+                ('a'..='z').collect::<Vec<_>>(),
+                "{}",
+                layout.name()
+            );
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tower_sessions::{session::Id, Session};
+
+use crate::config::Config;
+use crate::error::{AppError, JsonError};
+use crate::{metrics, AppState};
+
+// Buckets are pruned once a limiter tracks this many keys, and after that whenever the
+// number of keys has doubled since the last prune
+const PRUNE_THRESHOLD: usize = 10_000;
+
+// A token bucket refilled at `rate` tokens per second, holding at most `burst` tokens
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Limit {
+    pub rate: f64,
+    pub burst: u32,
+}
+
+impl Limit {
+    pub fn new(rate: f64, burst: u32) -> Self {
+        Self { rate, burst }
+    }
+}
+
+// Parses limits written as "rate:burst", such as "0.5:3"
+impl std::str::FromStr for Limit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid limit '{}', expected rate:burst", s);
+        let (rate, burst) = s.split_once(':').ok_or_else(invalid)?;
+        Ok(Self {
+            rate: rate.trim().parse().map_err(|_| invalid())?,
+            burst: burst.trim().parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+#[derive(Debug)]
+struct Buckets<K> {
+    keys: HashMap<K, Bucket>,
+    // number of keys after the last prune
+    pruned: usize,
+}
+
+#[derive(Debug)]
+pub struct Limiter<K> {
+    limit: Limit,
+    buckets: Mutex<Buckets<K>>,
+}
+
+impl<K: Hash + Eq> Limiter<K> {
+    pub fn new(limit: Limit) -> Self {
+        Self {
+            limit,
+            buckets: Mutex::new(Buckets {
+                keys: HashMap::new(),
+                pruned: 0,
+            }),
+        }
+    }
+
+    // Takes a token for the key. Returns how long to wait for the next token when there are none left.
+    pub fn check(&self, key: K, now: Instant) -> Result<(), Duration> {
+        let Limit { rate, burst } = self.limit;
+        let mut buckets = self.buckets.lock().unwrap();
+        // waiting for the keys to double keeps the cost of pruning amortized constant, even
+        // when most buckets are still in use and survive it
+        if buckets.keys.len() >= PRUNE_THRESHOLD.max(buckets.pruned * 2) {
+            // buckets that have filled up again are the same as new ones
+            buckets.keys.retain(|_, bucket| {
+                bucket.tokens + now.saturating_duration_since(bucket.updated).as_secs_f64() * rate
+                    < burst as f64
+            });
+            buckets.pruned = buckets.keys.len();
+        }
+
+        let bucket = buckets.keys.entry(key).or_insert(Bucket {
+            tokens: burst as f64,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst as f64);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+    }
+}
+
+// Limits for one group of routes, tracked separately per session and per client address
+#[derive(Debug)]
+pub struct RouteLimits {
+    session: Limiter<Id>,
+    ip: Limiter<IpAddr>,
+}
+
+impl RouteLimits {
+    fn new(session: Limit, ip: Limit) -> Self {
+        Self {
+            session: Limiter::new(session),
+            ip: Limiter::new(ip),
+        }
+    }
+
+    fn check(&self, session: Option<Id>, ip: Option<IpAddr>) -> Result<(), Duration> {
+        let now = Instant::now();
+        if let Some(ip) = ip {
+            self.ip.check(ip, now)?;
+        }
+        // new sessions have no id until they are first saved
+        if let Some(session) = session {
+            self.session.check(session, now)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct RateLimits {
+    enabled: bool,
+    game: RouteLimits,
+    cheat: RouteLimits,
+}
+
+impl RateLimits {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            enabled: config.rate_limits,
+            game: RouteLimits::new(config.game_session_limit, config.game_ip_limit),
+            cheat: RouteLimits::new(config.cheat_session_limit, config.cheat_ip_limit),
+        }
+    }
+}
+
+// Passes the request on if it is within the limits. Otherwise answers with a 429, as a JSON
+// error for the JSON API and as an HTML fragment for the page.
+async fn limit(
+    limits: &RouteLimits,
+    route: &str,
+    json: bool,
+    request: Request,
+    next: Next,
+) -> Response {
+    let session = request
+        .extensions()
+        .get::<Session>()
+        .and_then(|session| session.id());
+    let ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    match limits.check(session, ip) {
+        Ok(()) => next.run(request).await,
+        Err(retry) => {
+            metrics::rate_limited(route);
+            let err = AppError::TooManyRequests(retry);
+            if json {
+                return JsonError(err).into_response();
+            }
+            err.into_response()
+        }
+    }
+}
+
+// Middleware limiting the game input routes
+pub async fn game(State(app): State<AppState>, request: Request, next: Next) -> Response {
+    if !app.limits.enabled {
+        return next.run(request).await;
+    }
+    limit(&app.limits.game, "game", false, request, next).await
+}
+
+// Middleware limiting the JSON API like the game input routes
+pub async fn api(State(app): State<AppState>, request: Request, next: Next) -> Response {
+    if !app.limits.enabled {
+        return next.run(request).await;
+    }
+    limit(&app.limits.game, "game", true, request, next).await
+}
+
+// Middleware limiting the solver, which is far more expensive than anything else
+pub async fn cheat(State(app): State<AppState>, request: Request, next: Next) -> Response {
+    if !app.limits.enabled {
+        return next.run(request).await;
+    }
+    limit(&app.limits.cheat, "cheat", false, request, next).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_limit_parse() {
+        assert_eq!("0.5:3".parse::<Limit>(), Ok(Limit::new(0.5, 3)));
+        assert!("0.5".parse::<Limit>().is_err());
+        assert!("fast:3".parse::<Limit>().is_err());
+    }
+
+    #[test]
+    fn test_limiter() {
+        let limiter = Limiter::new(Limit::new(2.0, 3));
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(limiter.check("a", start), Ok(()));
+        }
+        let retry = limiter.check("a", start).unwrap_err();
+        assert_eq!(retry, Duration::from_millis(500));
+
+        // other keys have their own bucket
+        assert_eq!(limiter.check("b", start), Ok(()));
+
+        // one token is added every half second
+        let later = start + Duration::from_millis(500);
+        assert_eq!(limiter.check("a", later), Ok(()));
+        assert!(limiter.check("a", later).is_err());
+
+        // the bucket never holds more than the burst
+        let much_later = start + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert_eq!(limiter.check("a", much_later), Ok(()));
+        }
+        assert!(limiter.check("a", much_later).is_err());
+    }
+
+    #[test]
+    fn test_limiter_prune() {
+        let limiter = Limiter::new(Limit::new(1.0, 1));
+        let start = Instant::now();
+        // empty buckets survive pruning
+        for key in 0..=PRUNE_THRESHOLD {
+            limiter.check(key, start).unwrap();
+        }
+        assert_eq!(limiter.buckets.lock().unwrap().pruned, PRUNE_THRESHOLD);
+
+        // so nothing is pruned again until the keys have doubled
+        let later = start + Duration::from_secs(2);
+        for key in PRUNE_THRESHOLD + 1..2 * PRUNE_THRESHOLD {
+            limiter.check(key, later).unwrap();
+        }
+        assert_eq!(
+            limiter.buckets.lock().unwrap().keys.len(),
+            2 * PRUNE_THRESHOLD
+        );
+
+        // by then the first buckets have filled up again and are dropped
+        limiter.check(2 * PRUNE_THRESHOLD, later).unwrap();
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.pruned, PRUNE_THRESHOLD - 1);
+        assert_eq!(buckets.keys.len(), PRUNE_THRESHOLD);
+    }
+}
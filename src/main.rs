@@ -1,74 +1,345 @@
+mod api;
+mod assets;
+mod bench;
+// lints the original word, charset and filter code is written against
+#[allow(
+    clippy::manual_range_contains,
+    clippy::clone_on_copy,
+    clippy::bool_assert_comparison
+)]
 mod charset;
+mod config;
 mod dictionary;
+mod error;
+mod feedback;
+mod frequency;
+mod i18n;
+mod index;
+mod layout;
+mod limit;
+mod metrics;
+mod player;
+mod profile;
+mod query;
+mod share;
+mod solver;
 mod state;
+#[allow(clippy::clone_on_copy, clippy::bool_assert_comparison)]
 mod stats;
+mod store;
 mod templates;
+mod theme;
+#[allow(
+    clippy::manual_range_contains,
+    clippy::needless_return,
+    clippy::assign_op_pattern,
+    clippy::bool_assert_comparison
+)]
 mod word;
 
 use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
     routing::{get, post},
-    Form, Router,
+    Form, Json, Router,
 };
+use config::{Command, Config, SessionStoreKind};
+use error::AppError;
+use i18n::Locale;
+use limit::RateLimits;
 use maud::{html, Markup};
-use serde::Deserialize;
-use state::{GameState, Input};
+use player::{Settings, Stats};
+use profile::Profile;
+use serde::{Deserialize, Serialize};
+use solver::Solver;
+use state::{GameState, Input, Mode, Phase};
+use std::future::IntoFuture;
+use std::net::SocketAddr;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Instant;
+use store::{FileStore, Store};
+use subtle::ConstantTimeEq;
+use theme::Theme;
 use tokio::net::TcpListener;
-use tower_http::services::ServeDir;
-use tower_sessions::{cookie::time::Duration, Expiry, MemoryStore, Session, SessionManagerLayer};
+use tower::ServiceBuilder;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::{DefaultOnResponse, TraceLayer},
+};
+use tower_sessions::{
+    cookie::time::Duration, session, Expiry, MemoryStore, Session, SessionManagerLayer,
+};
+use tracing::{debug, error, info, info_span, instrument, warn, Level, Span};
+use word::Word;
 
 const STATE_KEY: &str = "game";
+const PLAYER_KEY: &str = "player";
+
+// Reads the game from the session. A game that no longer deserializes, e.g. one saved by an
+// older version, is dropped and treated as missing so the player starts a new one. Nothing
+// else in the session is touched, the player record is kept apart from it.
+async fn load_game(session: &Session) -> Result<Option<GameState>, AppError> {
+    match session.get(STATE_KEY).await {
+        Err(session::Error::SerdeJson(err)) => {
+            warn!(%err, "discarding corrupt game");
+            session.remove_value(STATE_KEY).await?;
+            Ok(None)
+        }
+        result => Ok(result?),
+    }
+}
+
+// The game in the session, or a new one if there is none, e.g. because the session expired.
+// New games are drawn for the player like any other, so the answer is not repeated and the
+// player's settings and the enabled modes apply.
+async fn current_game(
+    app: &AppState,
+    session: &Session,
+    profile: &mut Profile,
+) -> Result<GameState, AppError> {
+    if let Some(state) = load_game(session).await? {
+        return Ok(state);
+    }
+    let mut player = profile.load().await?;
+    let state = player.new_game(app.config.mode(Mode::Normal));
+    metrics::game_started(&state);
+    session.insert(STATE_KEY, state.clone()).await?;
+    profile.save(&player).await?;
+    Ok(state)
+}
+
+#[derive(Clone)]
+struct AppState {
+    config: Arc<Config>,
+    // set once the dictionaries are loaded
+    ready: Arc<AtomicBool>,
+    limits: Arc<RateLimits>,
+    solver: Arc<Solver>,
+    // where sessions and player records are kept
+    store: Store,
+}
 
-async fn page(session: Session) -> Markup {
-    let state = session.get(STATE_KEY).await.unwrap().unwrap_or_default();
+async fn page(
+    State(app): State<AppState>,
+    session: Session,
+    mut profile: Profile,
+    lang: Locale,
+) -> Result<Markup, AppError> {
+    let config = &app.config;
+    let state = current_game(&app, &session, &mut profile).await?;
+    let player = profile.load().await?;
 
-    return templates::page(
+    Ok(templates::page(
         "Wordle",
+        player.settings.theme,
+        lang,
         html! {
             form id="form" method="post" hx-post="/api/input" hx-target="#game" hx-swap="outerHTML" {
                 input type="hidden" name="key" id="key";
             }
             h1 { "Wordlx"}
-            (templates::game_board(&state))
+            (templates::game_board(&state, None, player.settings.layout, lang))
+            div #announce .sr-only role="status" aria-live="polite" {}
+            div #errors role="alert" {}
 
             div.panel {
-                button hx-get="/cheat" hx-target="#cheat"  { "Cheat" }
+                @if config.cheat {
+                    button hx-get="/cheat" hx-target="#cheat"  { (lang.text("cheat")) }
+                }
+                button hx-get="/stats" hx-target="#modal" { (lang.text("stats")) }
+                @for (mode, label) in [(Mode::Normal, "new_game"), (Mode::Easy, "easy_game"), (Mode::Daily, "daily")] {
+                    @if config.enabled(mode) {
+                        (templates::reset_button(mode, lang.text(label)))
+                    }
+                }
             }
+            (templates::settings(&player.settings, lang))
+            div #modal {}
             div #cheat {}
 
-            script src="assets/wordle.js" {}
+            script src=[assets::url("wordle.js")] {}
         },
-    );
+    ))
+}
+
+#[derive(Deserialize)]
+struct ResetParams {
+    #[serde(default)]
+    mode: Mode,
+}
+async fn reset(
+    State(app): State<AppState>,
+    session: Session,
+    mut profile: Profile,
+    lang: Locale,
+    Form(param): Form<ResetParams>,
+) -> Result<Markup, AppError> {
+    let mut player = profile.load().await?;
+    let state = player.new_game(app.config.mode(param.mode));
+    metrics::game_started(&state);
+    session.insert(STATE_KEY, state.clone()).await?;
+    profile.save(&player).await?;
+    Ok(templates::game_board(
+        &state,
+        None,
+        player.settings.layout,
+        lang,
+    ))
 }
 
-async fn reset(session: Session) -> Markup {
-    let state = GameState::new_random();
-    session.insert(STATE_KEY, state.clone()).await.unwrap();
-    templates::game_board(&state)
+async fn settings(
+    session: Session,
+    mut profile: Profile,
+    lang: Locale,
+    Form(settings): Form<Settings>,
+) -> Result<Response, AppError> {
+    let mut player = profile.load().await?;
+    let reload =
+        player.settings.theme != settings.theme || player.settings.locale != settings.locale;
+    player.settings = settings;
+    profile.save(&player).await?;
+    // the theme and language affect the whole page, so it is reloaded for a new one
+    if reload {
+        return Ok((
+            [("HX-Refresh", "true")],
+            templates::settings(&player.settings, lang),
+        )
+            .into_response());
+    }
+    let state: Option<GameState> = load_game(&session).await?;
+    Ok(html! {
+        (templates::settings(&player.settings, lang))
+        // redraw the keyboard in case the layout changed
+        @if let Some(state) = state.filter(|state| state.phase == Phase::Playing) {
+            (templates::keyboard(&state, player.settings.layout, lang, true))
+        }
+    }
+    .into_response())
 }
 
 #[derive(Deserialize)]
 struct InputParams {
     key: String,
 }
-async fn input(session: Session, Form(param): Form<InputParams>) -> Markup {
-    let mut state: GameState = session.get(STATE_KEY).await.unwrap().unwrap_or_default();
+// Stores the game, and records it in the player's stats if this update finished it.
+// Returns the updated stats when the game was finished.
+async fn save_game(
+    session: &Session,
+    profile: &mut Profile,
+    before: &GameState,
+    state: &GameState,
+) -> Result<Option<Stats>, AppError> {
+    session.insert(STATE_KEY, state.clone()).await?;
+    metrics::game_updated(before, state);
+    if before.phase != Phase::Playing || state.phase == Phase::Playing {
+        return Ok(None);
+    }
+    let mut player = profile.load().await?;
+    player.record(state);
+    profile.save(&player).await?;
+    Ok(Some(player.stats))
+}
+
+async fn input(
+    State(app): State<AppState>,
+    session: Session,
+    mut profile: Profile,
+    lang: Locale,
+    Form(param): Form<InputParams>,
+) -> Result<Markup, AppError> {
+    let mut state = current_game(&app, &session, &mut profile).await?;
+    let before = state.clone();
     if param.key == "enter" {
         state.input(Input::Enter);
     } else if param.key == "backspace" {
         state.input(Input::Backspace);
-    } else {
-        state.input(Input::Character(param.key.chars().next().unwrap()));
+    } else if let Some(c) = param.key.chars().next() {
+        state.input(Input::Character(c));
     }
-    session.insert(STATE_KEY, state.clone()).await.unwrap();
-    templates::game_board(&state)
+    let stats = save_game(&session, &mut profile, &before, &state).await?;
+    let player = profile.load().await?;
+    Ok(html! {
+        (templates::game_board(&state, stats.as_ref(), player.settings.layout, lang))
+        (templates::announcement(&before, &state, lang))
+    })
 }
 
-async fn cheat(session: Session) -> Markup {
-    let state: GameState = session.get(STATE_KEY).await.unwrap().unwrap_or_default();
+#[derive(Deserialize)]
+struct GuessParams {
+    guess: String,
+}
+async fn guess(
+    State(app): State<AppState>,
+    session: Session,
+    mut profile: Profile,
+    lang: Locale,
+    Form(param): Form<GuessParams>,
+) -> Result<Markup, AppError> {
+    let mut state = current_game(&app, &session, &mut profile).await?;
+    let before = state.clone();
+    // a rejected guess is shown on the board through state.error
+    let _ = state.guess(&param.guess);
+    let stats = save_game(&session, &mut profile, &before, &state).await?;
+    let player = profile.load().await?;
+    Ok(html! {
+        (templates::game_board(&state, stats.as_ref(), player.settings.layout, lang))
+        (templates::announcement(&before, &state, lang))
+    })
+}
 
-    if state.phase != state::Phase::Playing {
-        return html! {};
+async fn stats(profile: Profile, lang: Locale) -> Result<Markup, AppError> {
+    let player = profile.load().await?;
+    Ok(templates::stats_modal(&player.stats, None, lang))
+}
+
+async fn share(
+    session: Session,
+    profile: Profile,
+    lang: Locale,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let Some(state) = load_game(&session).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    if state.phase == Phase::Playing {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+
+    let player = profile.load().await?;
+    let text = share::share_text(&state, player.settings.theme);
+    let fragment = templates::share(&text, lang);
+    if headers.contains_key("HX-Request") {
+        return Ok(fragment.into_response());
+    }
+    // without htmx, serve a page the text can be copied from
+    Ok(templates::page(
+        lang.text("share"),
+        player.settings.theme,
+        lang,
+        html! { h1 { (lang.text("share")) } (fragment) },
+    )
+    .into_response())
+}
+
+#[instrument(skip_all)]
+async fn cheat(
+    State(app): State<AppState>,
+    session: Session,
+    mut profile: Profile,
+    lang: Locale,
+) -> Result<Response, AppError> {
+    if !app.config.cheat {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    metrics::cheat_requested();
+    let state = current_game(&app, &session, &mut profile).await?;
+
+    if state.phase != Phase::Playing {
+        return Ok(html! {}.into_response());
     }
 
     let mut filter = stats::WordFilter::new(state.answer);
@@ -78,56 +349,470 @@ async fn cheat(session: Session) -> Markup {
 
     let start_match = Instant::now();
     let choices = dictionary::WORDS
-        .iter()
-        .filter(|w| filter.matches(**w))
+        .filter(&filter)
+        .into_iter()
+        .filter(|w| !dictionary::WORDS.blocked(*w))
         .collect::<Vec<_>>();
-    println!("match took {:?}", start_match.elapsed());
+    metrics::cheat_stage("match", start_match.elapsed());
+    debug!(choices = choices.len(), elapsed = ?start_match.elapsed(), "matched");
 
-    // find the choice which minimizes the number of remaining possibilities
+    // find the choice which minimizes the expected number of remaining possibilities
     let start_score = Instant::now();
-    let mut scored = choices
-        .iter()
-        .map(|choice| {
-            let mut filter = filter.clone();
-            filter.reject(**choice);
-            let count = choices.iter().filter(|w| filter.matches(***w)).count();
-            return (**choice, count);
-        })
-        .collect::<Vec<_>>();
+    let Some(ranking) = app.solver.rank(filter.clone(), choices.clone()).await else {
+        let busy = html! { p.message.error { (lang.text("solver_busy")) } };
+        return Ok((
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(header::RETRY_AFTER, "1")],
+            busy,
+        )
+            .into_response());
+    };
+    metrics::cheat_stage("score", start_score.elapsed());
+    debug!(elapsed = ?start_score.elapsed(), complete = ranking.complete, "scored");
+    metrics::cheat_stage("total", start_match.elapsed());
 
-    println!("score took {:?}", start_score.elapsed());
-    println!("cheat took {:?}", start_match.elapsed());
+    let letters = frequency::LetterStats::new(&choices);
 
-    scored.sort_by(|a, b| a.1.cmp(&b.1));
-
-    html! {
-        h2 { (choices.len()) " choices" }
-        (templates::guess_table(html! {
-            @for (word, score) in scored.iter() {
-                tr { td { (score) } }
-                (templates::guess_row(word.clone(), filter.correct, filter.required, false))
+    Ok(html! {
+        h2 { (lang.plural("choices_one", "choices", choices.len())) }
+        @if !ranking.complete {
+            p.message.small {
+                (lang.plural("partial_ranking_one", "partial_ranking", ranking.scored.len()))
+            }
+        }
+        (templates::informative_letters(&letters, lang))
+        (templates::guess_table(lang.text("suggestions"), html! {
+            @for (word, score) in ranking.scored.iter() {
+                tr { td { (format!("{:.1}", score)) } }
+                (templates::guess_row(*word, filter.correct, filter.required, false, lang))
             }
         }))
     }
+    .into_response())
+}
+
+// Returns true if the client prefers a JSON response over HTML
+fn wants_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .map(|accept| accept.contains("application/json"))
+        .unwrap_or(false)
+}
+
+#[derive(Deserialize, Default)]
+struct WordsParams {
+    pattern: Option<String>,
+    contains: Option<String>,
+    excludes: Option<String>,
+    counts: Option<String>,
+    list: Option<String>,
+}
+
+impl WordsParams {
+    fn query(&self) -> Result<query::Query, query::QueryError> {
+        let mut query = query::Query::new();
+        if let Some(pattern) = self.pattern.as_deref().filter(|p| !p.is_empty()) {
+            query = query.pattern(pattern)?;
+        }
+        if let Some(contains) = &self.contains {
+            query = query.contains(contains)?;
+        }
+        if let Some(excludes) = &self.excludes {
+            query = query.excludes(excludes)?;
+        }
+        if let Some(counts) = &self.counts {
+            query = query.counts(counts)?;
+        }
+        Ok(query)
+    }
+
+    fn all(&self) -> bool {
+        self.list.as_deref() == Some("all")
+    }
+
+    fn words(&self) -> Result<Vec<Word>, query::QueryError> {
+        let query = self.query()?;
+        let mut words = dictionary::WORDS.query(&query);
+        if self.all() {
+            words.extend(dictionary::GUESSES.query(&query));
+            words.sort_by_key(|w| w.to_string());
+        }
+        Ok(words)
+    }
+
+    fn form(&self, action: &str, lang: Locale) -> Markup {
+        html! {
+            form.query method="get" action=(action) {
+                input type="text" name="pattern" placeholder="a?e??" value=[&self.pattern];
+                input type="text" name="contains" placeholder=(lang.text("contains")) value=[&self.contains];
+                input type="text" name="excludes" placeholder=(lang.text("excludes")) value=[&self.excludes];
+                input type="text" name="counts" placeholder=(lang.text("counts")) value=[&self.counts];
+                select name="list" {
+                    option value="answers" { (lang.text("answers")) }
+                    option value="all" selected[self.all()] { (lang.text("all_guesses")) }
+                }
+                button type="submit" { (lang.text("search")) }
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct WordsResponse {
+    count: usize,
+    words: Vec<String>,
+}
+
+// The theme of the player, for pages that otherwise do not need the player
+async fn page_theme(profile: &Profile) -> Result<Theme, AppError> {
+    Ok(profile.load().await?.settings.theme)
+}
+
+fn bad_query(
+    headers: &HeaderMap,
+    theme: Theme,
+    lang: Locale,
+    title: &str,
+    params: &WordsParams,
+    err: query::QueryError,
+) -> Response {
+    if wants_json(headers) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": err.message(lang) })),
+        )
+            .into_response();
+    }
+    let page = templates::page(
+        title,
+        theme,
+        lang,
+        html! {
+            h1 { (title) }
+            (params.form("?", lang))
+            p.message.error { (err.message(lang)) }
+        },
+    );
+    (StatusCode::BAD_REQUEST, page).into_response()
+}
+
+async fn words(
+    profile: Profile,
+    lang: Locale,
+    headers: HeaderMap,
+    Query(params): Query<WordsParams>,
+) -> Result<Response, AppError> {
+    let theme = page_theme(&profile).await?;
+    let words = match params.words() {
+        Ok(words) => words,
+        Err(err) => {
+            return Ok(bad_query(
+                &headers,
+                theme,
+                lang,
+                lang.text("words"),
+                &params,
+                err,
+            ))
+        }
+    };
+
+    if wants_json(&headers) {
+        return Ok(Json(WordsResponse {
+            count: words.len(),
+            words: words.iter().map(|w| w.to_string()).collect(),
+        })
+        .into_response());
+    }
+
+    Ok(templates::page(
+        lang.text("words"),
+        theme,
+        lang,
+        html! {
+            h1 { (lang.text("words")) }
+            (params.form("/words", lang))
+            h2 { (lang.plural("word_count_one", "word_count", words.len())) }
+            (templates::word_list(&words))
+        },
+    )
+    .into_response())
+}
+
+async fn letters(
+    profile: Profile,
+    lang: Locale,
+    headers: HeaderMap,
+    Query(params): Query<WordsParams>,
+) -> Result<Response, AppError> {
+    let theme = page_theme(&profile).await?;
+    let words = match params.words() {
+        Ok(words) => words,
+        Err(err) => {
+            return Ok(bad_query(
+                &headers,
+                theme,
+                lang,
+                lang.text("letters"),
+                &params,
+                err,
+            ))
+        }
+    };
+    let stats = frequency::LetterStats::new(&words);
+
+    if wants_json(&headers) {
+        return Ok(Json(stats).into_response());
+    }
+
+    Ok(templates::page(
+        lang.text("letters"),
+        theme,
+        lang,
+        html! {
+            h1 { (lang.text("letters")) }
+            (params.form("/letters", lang))
+            p.message.small { (lang.plural("word_count_one", "word_count", stats.words)) }
+            (templates::letter_stats(&stats, lang))
+        },
+    )
+    .into_response())
+}
+
+// Reloads the answer blocklist and guess allowlist. Requires the admin token as a bearer token,
+// and is disabled unless an admin token is configured.
+async fn reload_overlays(State(app): State<AppState>, headers: HeaderMap) -> Response {
+    let Some(token) = &app.config.admin_token else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let authorized = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        // compared in constant time so the token can't be guessed from response times
+        .map(|value| bool::from(value.as_bytes().ct_eq(token.as_bytes())))
+        .unwrap_or(false);
+    if !authorized {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    match dictionary::load_overlays() {
+        Ok((blocked, allowed)) => {
+            // blocked words are left out of the cached rankings
+            app.solver.clear_cache();
+            Json(serde_json::json!({
+                "blocked": blocked,
+                "allowed": allowed,
+            }))
+            .into_response()
+        }
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": err.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+// Liveness probe, the server is up as long as it answers
+async fn healthz() -> &'static str {
+    "ok"
+}
+
+// Readiness probe, reports whether the dictionaries and their indexes have been built
+async fn readyz(State(app): State<AppState>) -> Response {
+    let dictionaries = app.ready.load(Ordering::Acquire);
+    let status = if dictionaries {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let body = Json(serde_json::json!({
+        "ready": dictionaries,
+        "dictionaries": dictionaries,
+    }));
+    (status, body).into_response()
+}
+
+// Every request is logged in a span carrying its request id
+fn request_span(request: &axum::extract::Request) -> Span {
+    let id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default();
+    info_span!(
+        "request",
+        id,
+        method = %request.method(),
+        uri = %request.uri(),
+    )
+}
+
+// Resolves on SIGINT or SIGTERM
+async fn shutdown_signal() {
+    let interrupt = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to listen for ctrl-c");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {},
+        _ = terminate => {},
+    }
+    info!("shutting down, draining requests");
+}
+
+// Loads the dictionaries and their overlays, and checks that easy mode has word frequencies.
+// Returns the number of blocked and allowed words.
+fn load_dictionaries(easy: bool) -> Result<(usize, usize), String> {
+    let counts = dictionary::load_overlays()
+        .map_err(|err| format!("failed to load word list overlays: {}", err))?;
+    if easy && !dictionary::WORDS.weighted() {
+        return Err("easy mode is enabled but no answer has a word frequency".to_string());
+    }
+    Ok(counts)
 }
 
 #[tokio::main]
-async fn main() {
-    let session_store = MemoryStore::default();
-    let session_layer = SessionManagerLayer::new(session_store)
-        .with_secure(false)
-        .with_expiry(Expiry::OnInactivity(Duration::seconds(3600)));
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load()?;
+    config.init_logging();
+    dictionary::configure(config.sources());
+    if let Some(Command::Bench { rounds }) = config.command {
+        bench::run(rounds);
+        return Ok(());
+    }
+
+    assets::check()?;
+
+    // load the dictionaries in the background so health checks are answered right away,
+    // a failure stops the server below
+    let ready = Arc::new(AtomicBool::new(false));
+    let loaded = ready.clone();
+    let easy = config.enabled(Mode::Easy);
+    let (failed, failure) = tokio::sync::oneshot::channel();
+    tokio::task::spawn_blocking(move || match load_dictionaries(easy) {
+        Ok((blocked, allowed)) => {
+            info!(blocked, allowed, "dictionaries loaded");
+            loaded.store(true, Ordering::Release);
+        }
+        Err(err) => {
+            let _ = failed.send(err);
+        }
+    });
+
+    let session_store = match config.session_store {
+        SessionStoreKind::File => {
+            Store::File(FileStore::new(&config.session_dir).map_err(|err| {
+                format!("failed to open {}: {}", config.session_dir.display(), err)
+            })?)
+        }
+        SessionStoreKind::Memory => Store::Memory(MemoryStore::default()),
+    };
+    let cleanup = tokio::spawn(
+        session_store
+            .clone()
+            .delete_expired_every(std::time::Duration::from_secs(600)),
+    );
+
+    let session_layer = SessionManagerLayer::new(session_store.clone())
+        .with_secure(config.secure_cookies)
+        .with_expiry(Expiry::OnInactivity(Duration::seconds(
+            config.session_expiry as i64,
+        )));
+
+    let addr = config.addr();
+    let state = AppState {
+        store: session_store.clone(),
+        limits: Arc::new(RateLimits::new(&config)),
+        solver: Arc::new(Solver::new(
+            config.solver_workers,
+            std::time::Duration::from_millis(config.solver_budget),
+            config.solver_cache,
+        )),
+        config: Arc::new(config),
+        ready,
+    };
+    let game_limit = middleware::from_fn_with_state(state.clone(), limit::game);
 
     let app = Router::new()
         .route("/", get(page))
-        .route("/cheat", get(cheat))
-        .route("/api/input", post(input))
-        .route("/api/reset", post(reset))
-        .nest_service("/assets", ServeDir::new("assets"))
-        .layer(session_layer);
-
-    let port = 8080;
-    let addr = format!("0.0.0.0:{}", port);
-    let listener = TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/metrics", get(metrics::render))
+        .route(
+            "/cheat",
+            get(cheat).route_layer(middleware::from_fn_with_state(state.clone(), limit::cheat)),
+        )
+        .route("/stats", get(stats))
+        .route("/share", get(share))
+        .route("/words", get(words))
+        .route("/letters", get(letters))
+        .route("/api/input", post(input).route_layer(game_limit.clone()))
+        .route("/api/guess", post(guess).route_layer(game_limit.clone()))
+        .route("/api/reset", post(reset).route_layer(game_limit.clone()))
+        .route("/api/settings", post(settings))
+        .route("/admin/reload", post(reload_overlays))
+        .nest(
+            "/api/v1",
+            api::router().route_layer(middleware::from_fn_with_state(state.clone(), limit::api)),
+        )
+        .route("/assets/*path", get(assets::serve))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            error::localize,
+        ))
+        .layer(session_layer)
+        .layer(middleware::from_fn(metrics::track))
+        .layer(
+            ServiceBuilder::new()
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(
+                    TraceLayer::new_for_http()
+                        .make_span_with(request_span)
+                        .on_response(DefaultOnResponse::new().level(Level::INFO)),
+                )
+                .layer(PropagateRequestIdLayer::x_request_id()),
+        )
+        .with_state(state);
+
+    let listener = TcpListener::bind(&addr).await?;
+    info!(%addr, "listening");
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal());
+    let result = tokio::select! {
+        result = server.into_future() => result.map_err(Into::into),
+        Ok(err) = failure => Err(err.into()),
+    };
+
+    cleanup.abort();
+    if let Err(err) = session_store.close() {
+        error!(%err, "failed to flush sessions");
+    }
+    result
 }
@@ -0,0 +1,230 @@
+use axum::{
+    extract::{MatchedPath, Request},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge, Encoder, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    TextEncoder,
+};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tower_sessions::{cookie::time::OffsetDateTime, session::Id};
+
+use crate::state::{Error, GameState, Phase};
+
+lazy_static! {
+    static ref GAMES_STARTED: IntCounterVec = register_int_counter_vec!(
+        "wordlx_games_started_total",
+        "Games started, by mode",
+        &["mode"]
+    )
+    .unwrap();
+    static ref GAMES_FINISHED: IntCounterVec = register_int_counter_vec!(
+        "wordlx_games_finished_total",
+        "Games finished, by mode and outcome",
+        &["mode", "outcome"]
+    )
+    .unwrap();
+    static ref GUESSES_PER_GAME: HistogramVec = register_histogram_vec!(
+        "wordlx_guesses_per_game",
+        "Guesses used in finished games, by mode and outcome",
+        &["mode", "outcome"],
+        vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]
+    )
+    .unwrap();
+    static ref GUESSES: IntCounterVec = register_int_counter_vec!(
+        "wordlx_guesses_total",
+        "Submitted guesses, by whether they were accepted",
+        &["result"]
+    )
+    .unwrap();
+    static ref CHEAT_REQUESTS: IntCounter =
+        register_int_counter!("wordlx_cheat_requests_total", "Requests for solver hints").unwrap();
+    static ref CHEAT_DURATION: HistogramVec = register_histogram_vec!(
+        "wordlx_cheat_duration_seconds",
+        "Time spent producing solver hints, by stage",
+        &["stage"]
+    )
+    .unwrap();
+    static ref SOLVER_CACHE: IntCounterVec = register_int_counter_vec!(
+        "wordlx_solver_cache_total",
+        "Solver ranking cache lookups, by whether they hit",
+        &["result"]
+    )
+    .unwrap();
+    static ref RATE_LIMITED: IntCounterVec = register_int_counter_vec!(
+        "wordlx_rate_limited_total",
+        "Requests rejected by rate limits, by route group",
+        &["group"]
+    )
+    .unwrap();
+    static ref ACTIVE_SESSIONS: IntGauge =
+        register_int_gauge!("wordlx_active_sessions", "Sessions that have not expired").unwrap();
+    static ref REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "wordlx_request_duration_seconds",
+        "HTTP request latencies, by route, method and status",
+        &["route", "method", "status"]
+    )
+    .unwrap();
+    static ref UNMATCHED_DURATION: Histogram = register_histogram!(
+        "wordlx_unmatched_request_duration_seconds",
+        "Latencies of requests that matched no route"
+    )
+    .unwrap();
+
+    // expiry dates of the sessions saved since startup
+    static ref SESSIONS: Mutex<Sessions> = Mutex::new(Sessions::default());
+}
+
+// Map sizes below this are never pruned on insert
+const PRUNE_MIN: usize = 1024;
+
+// Expiry of every saved session, for counting the active ones
+#[derive(Default)]
+struct Sessions {
+    expiry: HashMap<Id, OffsetDateTime>,
+    // size of the map after it was last pruned
+    pruned: usize,
+}
+
+impl Sessions {
+    fn insert(&mut self, id: Id, expiry: OffsetDateTime) {
+        self.expiry.insert(id, expiry);
+        // pruned on insert once the map has doubled, so sessions that expire without being
+        // deleted don't pile up between scrapes, at an amortized constant cost
+        if self.expiry.len() >= PRUNE_MIN.max(self.pruned * 2) {
+            self.prune();
+        }
+    }
+
+    fn prune(&mut self) {
+        let now = OffsetDateTime::now_utc();
+        self.expiry.retain(|_, expiry| *expiry > now);
+        self.pruned = self.expiry.len();
+    }
+}
+
+pub fn game_started(state: &GameState) {
+    // a daily the player already finished is shown again, not started
+    if state.phase != Phase::Playing {
+        return;
+    }
+    GAMES_STARTED
+        .with_label_values(&[state.mode.as_str()])
+        .inc();
+}
+
+// Records a guess being submitted, and the outcome if it finished the game
+pub fn game_updated(before: &GameState, state: &GameState) {
+    if state.error != Error::None {
+        GUESSES.with_label_values(&["invalid"]).inc();
+    } else if state.guesses.len() > before.guesses.len() {
+        GUESSES.with_label_values(&["valid"]).inc();
+    }
+
+    if before.phase != Phase::Playing || state.phase == Phase::Playing {
+        return;
+    }
+    let outcome = match state.phase {
+        Phase::Won => "won",
+        _ => "lost",
+    };
+    let labels = [state.mode.as_str(), outcome];
+    GAMES_FINISHED.with_label_values(&labels).inc();
+    GUESSES_PER_GAME
+        .with_label_values(&labels)
+        .observe(state.guesses.len() as f64);
+}
+
+pub fn cheat_requested() {
+    CHEAT_REQUESTS.inc();
+}
+
+pub fn cheat_stage(stage: &str, elapsed: Duration) {
+    CHEAT_DURATION
+        .with_label_values(&[stage])
+        .observe(elapsed.as_secs_f64());
+}
+
+pub fn solver_cache(hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    SOLVER_CACHE.with_label_values(&[result]).inc();
+}
+
+pub fn rate_limited(group: &str) {
+    RATE_LIMITED.with_label_values(&[group]).inc();
+}
+
+pub fn session_saved(id: Id, expiry: OffsetDateTime) {
+    SESSIONS.lock().unwrap().insert(id, expiry);
+}
+
+pub fn session_deleted(id: &Id) {
+    SESSIONS.lock().unwrap().expiry.remove(id);
+}
+
+// Middleware recording the latency of every request
+pub async fn track(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
+    let method = request.method().clone();
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let elapsed = start.elapsed().as_secs_f64();
+
+    match route {
+        Some(route) => REQUEST_DURATION
+            .with_label_values(&[&route, method.as_str(), response.status().as_str()])
+            .observe(elapsed),
+        None => UNMATCHED_DURATION.observe(elapsed),
+    }
+    response
+}
+
+pub async fn render() -> Response {
+    {
+        let mut sessions = SESSIONS.lock().unwrap();
+        sessions.prune();
+        ACTIVE_SESSIONS.set(sessions.expiry.len() as i64);
+    }
+
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    encoder
+        .encode(&prometheus::gather(), &mut buffer)
+        .expect("failed to encode metrics");
+    (
+        [(header::CONTENT_TYPE, encoder.format_type().to_string())],
+        buffer,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tower_sessions::cookie::time::Duration;
+
+    #[test]
+    fn test_sessions_pruned_on_insert() {
+        let mut sessions = Sessions::default();
+        let expired = OffsetDateTime::now_utc() - Duration::hours(1);
+        for _ in 0..PRUNE_MIN * 3 {
+            sessions.insert(Id::default(), expired);
+        }
+        assert!(sessions.expiry.len() < PRUNE_MIN);
+
+        let active = OffsetDateTime::now_utc() + Duration::hours(1);
+        for _ in 0..PRUNE_MIN * 3 {
+            sessions.insert(Id::default(), active);
+        }
+        assert!(sessions.expiry.len() >= PRUNE_MIN * 3);
+    }
+}
@@ -0,0 +1,299 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use tracing::warn;
+
+use crate::dictionary::{self, WORDS};
+use crate::i18n::{self, Locale};
+use crate::layout::Layout;
+use crate::state::{GameState, Mode, Phase};
+use crate::theme::Theme;
+use crate::word::Word;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    // Keep answers from daily games out of the random rotation
+    #[serde(default)]
+    pub exclude_daily: bool,
+    // New games are started in hard mode
+    #[serde(default)]
+    pub hard: bool,
+    // Layout of the on-screen keyboard
+    #[serde(default)]
+    pub layout: Layout,
+    #[serde(default)]
+    pub theme: Theme,
+    // Language of the interface, instead of the one the browser asks for
+    #[serde(default, deserialize_with = "i18n::deserialize_override")]
+    pub locale: Option<Locale>,
+}
+
+impl Settings {
+    fn salvage(value: &Value) -> Self {
+        Self {
+            exclude_daily: field(value, "exclude_daily"),
+            hard: field(value, "hard"),
+            layout: field(value, "layout"),
+            theme: field(value, "theme"),
+            locale: value
+                .get("locale")
+                .and_then(|locale| i18n::deserialize_override(locale).ok())
+                .flatten(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stats {
+    pub played: u32,
+    pub won: u32,
+    #[serde(default)]
+    pub current_streak: u32,
+    #[serde(default)]
+    pub max_streak: u32,
+    // number of games won with 1 through 6 guesses
+    #[serde(default)]
+    pub distribution: [u32; 6],
+}
+
+impl Stats {
+    pub fn win_percentage(&self) -> u32 {
+        if self.played == 0 {
+            return 0;
+        }
+        (self.won as f64 / self.played as f64 * 100.0).round() as u32
+    }
+}
+
+// How a daily game ended
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailyResult {
+    pub answer: Word,
+    pub phase: Phase,
+    pub guesses: Vec<Word>,
+}
+
+// Everything we remember about a player across games
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Player {
+    #[serde(default)]
+    pub settings: Settings,
+    #[serde(default)]
+    pub stats: Stats,
+    // Answers drawn since the answer pool was last used up
+    #[serde(default)]
+    pub seen: HashSet<Word>,
+    // Answers of daily games the player has started
+    #[serde(default)]
+    pub daily: HashSet<Word>,
+    // The finished daily game by day, so it is shown again instead of being replayed
+    #[serde(default)]
+    pub results: BTreeMap<u32, DailyResult>,
+}
+
+// A field of a stored object, or its default if it is missing or no longer deserializes
+fn field<T: DeserializeOwned + Default>(value: &Value, name: &str) -> T {
+    value
+        .get(name)
+        .and_then(|field| T::deserialize(field).ok())
+        .unwrap_or_default()
+}
+
+impl Player {
+    // Reads a stored player. If it no longer deserializes as a whole, e.g. because a setting
+    // was saved by another version, it is read field by field and only the fields that can't
+    // be read are reset, so one bad setting doesn't cost the player their stats.
+    pub fn salvage(value: &Value) -> Self {
+        match Player::deserialize(value) {
+            Ok(player) => player,
+            Err(err) => {
+                warn!(%err, "resetting unreadable player fields");
+                Self {
+                    settings: value
+                        .get("settings")
+                        .map(Settings::salvage)
+                        .unwrap_or_default(),
+                    stats: field(value, "stats"),
+                    seen: field(value, "seen"),
+                    daily: field(value, "daily"),
+                    results: field(value, "results"),
+                }
+            }
+        }
+    }
+
+    // Whether nothing worth keeping has happened yet, no game finished and no setting changed
+    pub fn untouched(&self) -> bool {
+        self.stats.played == 0 && self.results.is_empty() && self.settings == Settings::default()
+    }
+
+    pub fn new_game(&mut self, mode: Mode) -> GameState {
+        let answer = match mode {
+            Mode::Normal => self.draw(false),
+            Mode::Easy => self.draw(true),
+            Mode::Daily => {
+                let day = dictionary::today();
+                if let Some(result) = self.results.get(&day) {
+                    return GameState {
+                        phase: result.phase,
+                        guesses: result.guesses.clone(),
+                        ..GameState::start(mode, result.answer)
+                    };
+                }
+                let answer = WORDS.daily(day);
+                self.daily.insert(answer);
+                answer
+            }
+        };
+        GameState {
+            hard: self.settings.hard,
+            ..GameState::start(mode, answer)
+        }
+    }
+
+    // Records the outcome of a finished game
+    pub fn record(&mut self, game: &GameState) {
+        let stats = &mut self.stats;
+        match game.phase {
+            Phase::Playing => return,
+            Phase::Won => {
+                stats.won += 1;
+                stats.current_streak += 1;
+                stats.max_streak = stats.max_streak.max(stats.current_streak);
+                stats.distribution[game.guesses.len() - 1] += 1;
+            }
+            Phase::Lost => stats.current_streak = 0,
+        }
+        stats.played += 1;
+
+        if game.mode == Mode::Daily {
+            // only the latest daily can be asked for again
+            self.results.retain(|day, _| *day > game.day);
+            self.results.insert(
+                game.day,
+                DailyResult {
+                    answer: game.answer,
+                    phase: game.phase,
+                    guesses: game.guesses.clone(),
+                },
+            );
+        }
+    }
+
+    // Draws an answer without replacement. Once every answer has been seen the rotation starts over.
+    fn draw(&mut self, common: bool) -> Word {
+        let answer = WORDS.pick(common, &self.skipped()).unwrap_or_else(|| {
+            self.seen.clear();
+            WORDS
+                .pick(common, &self.skipped())
+                .unwrap_or_else(|| WORDS.random())
+        });
+        self.seen.insert(answer);
+        answer
+    }
+
+    fn skipped(&self) -> HashSet<Word> {
+        let mut skip = self.seen.clone();
+        if self.settings.exclude_daily {
+            skip.extend(&self.daily);
+        }
+        skip
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_player_salvage() {
+        let value = serde_json::json!({
+            "settings": { "hard": true, "layout": "klingon", "theme": "light", "locale": "sv" },
+            "stats": { "played": 4, "won": 3 },
+            "seen": "not a list",
+        });
+        let player = Player::salvage(&value);
+        assert!(player.settings.hard);
+        assert_eq!(player.settings.layout, Layout::Qwerty);
+        assert_eq!(player.settings.theme, Theme::Light);
+        assert_eq!(player.settings.locale, Some(Locale::Sv));
+        assert_eq!(player.stats.played, 4);
+        assert_eq!(player.stats.won, 3);
+        assert!(player.seen.is_empty());
+    }
+
+    #[test]
+    fn test_player_no_repeats() {
+        let mut player = Player::default();
+        let first = player.new_game(Mode::Normal).answer;
+        let second = player.new_game(Mode::Normal).answer;
+        assert_ne!(first, second);
+        assert!(player.seen.contains(&first));
+        assert!(player.seen.contains(&second));
+    }
+
+    #[test]
+    fn test_player_record() {
+        let mut player = Player::default();
+        let mut play = |guesses: &[&str]| {
+            let mut game = GameState::new("crane");
+            for guess in guesses {
+                game.guess(guess).unwrap();
+            }
+            player.record(&game);
+        };
+        play(&["react", "crane"]);
+        play(&["crane"]);
+        play(&["react"; 6]);
+        play(&["react", "react", "crane"]);
+        play(&["react"]);
+
+        let stats = &player.stats;
+        assert_eq!(stats.played, 4);
+        assert_eq!(stats.won, 3);
+        assert_eq!(stats.win_percentage(), 75);
+        assert_eq!(stats.current_streak, 1);
+        assert_eq!(stats.max_streak, 2);
+        assert_eq!(stats.distribution, [1, 1, 1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_player_pool_exhausted() {
+        let mut player = Player {
+            seen: WORDS.iter().copied().collect(),
+            ..Default::default()
+        };
+        let answer = player.new_game(Mode::Normal).answer;
+        assert_eq!(player.seen.len(), 1);
+        assert!(player.seen.contains(&answer));
+    }
+
+    #[test]
+    fn test_player_daily_result() {
+        let mut player = Player::default();
+        let mut game = player.new_game(Mode::Daily);
+        let answer = game.answer.to_string();
+        game.guess(&answer).unwrap();
+        player.record(&game);
+
+        let again = player.new_game(Mode::Daily);
+        assert_eq!(again.phase, Phase::Won);
+        assert_eq!(again.answer, game.answer);
+        assert_eq!(again.guesses, game.guesses);
+        // showing the result again doesn't count as another game
+        assert_eq!(player.stats.played, 1);
+        assert_eq!(player.results.len(), 1);
+    }
+
+    #[test]
+    fn test_player_exclude_daily() {
+        let mut player = Player::default();
+        player.settings.exclude_daily = true;
+        let daily = player.new_game(Mode::Daily).answer;
+        assert!(player.daily.contains(&daily));
+
+        player.seen = WORDS.iter().copied().filter(|w| *w != daily).collect();
+        let answer = player.new_game(Mode::Normal).answer;
+        assert_ne!(answer, daily);
+    }
+}
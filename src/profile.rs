@@ -0,0 +1,193 @@
+use async_trait::async_trait;
+use axum::{extract::FromRequestParts, http::request::Parts, http::StatusCode};
+use tower_cookies::Cookies;
+use tower_sessions::{
+    cookie::{
+        time::{Duration, OffsetDateTime},
+        Cookie, SameSite,
+    },
+    session::{self, Id, Record},
+    Session, SessionStore,
+};
+use tracing::warn;
+
+use crate::error::AppError;
+use crate::player::Player;
+use crate::store::Store;
+use crate::{AppState, PLAYER_KEY};
+
+// Cookie holding the id of the player record
+const COOKIE: &str = "wordlx.player";
+
+// The player record of a request. It is kept in the session store like a session, but under
+// its own cookie and with a much longer expiry than the game session, so stats, seen answers
+// and daily results are not lost when a game session expires after an idle hour.
+pub struct Profile {
+    id: Option<Id>,
+    cookies: Cookies,
+    store: Store,
+    // how long the record is kept after it was last saved
+    expiry: Duration,
+    // how long an untouched record is kept
+    session_expiry: Duration,
+    secure: bool,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for Profile {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        app: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let cookies = Cookies::from_request_parts(parts, app).await?;
+        let id = cookies
+            .get(COOKIE)
+            .and_then(|cookie| cookie.value().parse().ok());
+        let mut profile = Self {
+            id,
+            cookies,
+            store: app.store.clone(),
+            expiry: Duration::seconds(app.config.player_expiry as i64),
+            session_expiry: Duration::seconds(app.config.session_expiry as i64),
+            secure: app.config.secure_cookies,
+        };
+        if profile.id.is_none() {
+            if let Ok(session) = Session::from_request_parts(parts, app).await {
+                if let Err(err) = profile.migrate(&session).await {
+                    warn!(?err, "failed to move player out of the session");
+                }
+            }
+        }
+        Ok(profile)
+    }
+}
+
+impl Profile {
+    // The player, or a new one if they have no record yet
+    pub async fn load(&self) -> Result<Player, AppError> {
+        let Some(id) = self.id else {
+            return Ok(Player::default());
+        };
+        let record = self.store.load(&id).await.map_err(session::Error::Store)?;
+        let Some(value) = record.and_then(|mut record| record.data.remove(PLAYER_KEY)) else {
+            return Ok(Player::default());
+        };
+        Ok(Player::salvage(&value))
+    }
+
+    // Moves a player kept in the game session by earlier versions into a record of its own,
+    // so their stats, seen answers and daily results are kept
+    async fn migrate(&mut self, session: &Session) -> Result<(), AppError> {
+        let Some(value) = session.get_value(PLAYER_KEY).await? else {
+            return Ok(());
+        };
+        self.save(&Player::salvage(&value)).await?;
+        session.remove_value(PLAYER_KEY).await?;
+        Ok(())
+    }
+
+    // Saves the player and extends the life of the record and its cookie. A record of a player
+    // who hasn't finished a game or changed a setting, e.g. one made for a crawler loading
+    // the page, only lives as long as a game session.
+    pub async fn save(&mut self, player: &Player) -> Result<(), AppError> {
+        let id = *self.id.get_or_insert_with(Id::default);
+        let expiry = if player.untouched() {
+            self.session_expiry
+        } else {
+            self.expiry
+        };
+        let value = serde_json::to_value(player).map_err(session::Error::SerdeJson)?;
+        let record = Record {
+            id,
+            data: [(PLAYER_KEY.to_string(), value)].into(),
+            expiry_date: OffsetDateTime::now_utc() + expiry,
+        };
+        self.store
+            .save_player(&record)
+            .await
+            .map_err(session::Error::Store)?;
+
+        let cookie = Cookie::build((COOKIE, id.to_string()))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .secure(self.secure)
+            .max_age(expiry);
+        self.cookies.add(cookie.into());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::Mode;
+    use tower_sessions::MemoryStore;
+
+    fn profile(store: &Store, id: Option<Id>) -> Profile {
+        Profile {
+            id,
+            cookies: Cookies::default(),
+            store: store.clone(),
+            expiry: Duration::days(365),
+            session_expiry: Duration::hours(1),
+            secure: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_profile_outlives_session() {
+        let store = Store::Memory(MemoryStore::default());
+        let mut first = profile(&store, None);
+        let mut player = first.load().await.unwrap();
+        player.new_game(Mode::Normal);
+        player.new_game(Mode::Daily);
+        player.stats.played = 1;
+        first.save(&player).await.unwrap();
+
+        // a later request with only the player cookie, after the game session expired
+        let cookie = first.cookies.get(COOKIE).unwrap();
+        assert_eq!(cookie.max_age(), Some(Duration::days(365)));
+        let later = profile(&store, cookie.value().parse().ok());
+        let loaded = later.load().await.unwrap();
+        assert_eq!(loaded.seen, player.seen);
+        assert_eq!(loaded.daily, player.daily);
+        assert_eq!(loaded.seen.len(), 1);
+        assert_eq!(loaded.daily.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_profile_untouched_expiry() {
+        let store = Store::Memory(MemoryStore::default());
+        let mut profile = profile(&store, None);
+        let mut player = profile.load().await.unwrap();
+        player.new_game(Mode::Normal);
+        profile.save(&player).await.unwrap();
+        let cookie = profile.cookies.get(COOKIE).unwrap();
+        assert_eq!(cookie.max_age(), Some(Duration::hours(1)));
+
+        player.settings.hard = true;
+        profile.save(&player).await.unwrap();
+        let cookie = profile.cookies.get(COOKIE).unwrap();
+        assert_eq!(cookie.max_age(), Some(Duration::days(365)));
+    }
+
+    #[tokio::test]
+    async fn test_profile_migrate() {
+        let store = Store::Memory(MemoryStore::default());
+        let session = Session::new(None, std::sync::Arc::new(store.clone()), None);
+        let mut player = Player::default();
+        player.new_game(Mode::Normal);
+        player.stats.played = 3;
+        session.insert(PLAYER_KEY, &player).await.unwrap();
+
+        let mut profile = profile(&store, None);
+        profile.migrate(&session).await.unwrap();
+        let loaded = profile.load().await.unwrap();
+        assert_eq!(loaded.seen, player.seen);
+        assert_eq!(loaded.stats.played, 3);
+        assert_eq!(session.get_value(PLAYER_KEY).await.unwrap(), None);
+    }
+}
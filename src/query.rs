@@ -0,0 +1,194 @@
+use crate::charset::Charset;
+use crate::i18n::Locale;
+use crate::stats::WordSpace;
+use crate::word::Word;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryError {
+    PatternLength,
+    InvalidCharacter(char),
+    InvalidCount,
+}
+
+impl QueryError {
+    // The error in the language of the page
+    pub fn message(&self, lang: Locale) -> String {
+        match self {
+            QueryError::PatternLength => lang.text("error_pattern_length").to_string(),
+            QueryError::InvalidCharacter(c) => {
+                lang.format("error_query_character", &[("character", c)])
+            }
+            QueryError::InvalidCount => lang.text("error_letter_counts").to_string(),
+        }
+    }
+}
+
+// A constraint on how many times a letter may occur in a word
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LetterCount {
+    pub letter: char,
+    pub min: usize,
+    pub max: usize,
+}
+
+// A crossword style word lookup. Positions are constrained through a WordSpace,
+// while letters that must or must not occur anywhere are kept as charsets.
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub space: WordSpace,
+    pub required: Charset,
+    pub excluded: Charset,
+    pub counts: Vec<LetterCount>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Self {
+            space: WordSpace::new(),
+            required: Charset::none(),
+            excluded: Charset::none(),
+            counts: vec![],
+        }
+    }
+
+    // Restricts positions using a pattern such as "a?e??", where ? (or _ or .) matches any letter
+    pub fn pattern(mut self, pattern: &str) -> Result<Self, QueryError> {
+        let pattern = pattern.to_lowercase();
+        if pattern.chars().count() != 5 {
+            return Err(QueryError::PatternLength);
+        }
+        for (i, c) in pattern.chars().enumerate() {
+            match c {
+                '?' | '_' | '.' => {}
+                'a'..='z' => self.space.only(i, c),
+                _ => return Err(QueryError::InvalidCharacter(c)),
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn contains(mut self, letters: &str) -> Result<Self, QueryError> {
+        self.required = self.required.union(parse_letters(letters)?);
+        Ok(self)
+    }
+
+    pub fn excludes(mut self, letters: &str) -> Result<Self, QueryError> {
+        self.excluded = self.excluded.union(parse_letters(letters)?);
+        Ok(self)
+    }
+
+    pub fn count(mut self, letter: char, min: usize, max: usize) -> Self {
+        self.counts.push(LetterCount { letter, min, max });
+        self
+    }
+
+    // Parses a comma separated list of letter counts, e.g. "e2,s0-1"
+    pub fn counts(mut self, counts: &str) -> Result<Self, QueryError> {
+        for spec in counts.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let mut chars = spec.chars();
+            let letter = chars.next().unwrap().to_ascii_lowercase();
+            if !letter.is_ascii_lowercase() {
+                return Err(QueryError::InvalidCharacter(letter));
+            }
+            let range = chars.as_str();
+            let (min, max) = match range.split_once('-') {
+                Some((min, max)) => (min.parse(), max.parse()),
+                None => (range.parse(), range.parse()),
+            };
+            match (min, max) {
+                (Ok(min), Ok(max)) if min <= max => self = self.count(letter, min, max),
+                _ => return Err(QueryError::InvalidCount),
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn matches(&self, word: Word) -> bool {
+        let wm = word.charset();
+        if wm.contains_any(self.excluded) {
+            return false;
+        }
+        if !wm.contains_all(self.required) {
+            return false;
+        }
+        if !self.space.matches(word) {
+            return false;
+        }
+        self.counts.iter().all(|count| {
+            let n = word.count(count.letter);
+            n >= count.min && n <= count.max
+        })
+    }
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse_letters(letters: &str) -> Result<Charset, QueryError> {
+    let letters = letters.to_lowercase();
+    if let Some(c) = letters.chars().find(|c| !c.is_ascii_lowercase()) {
+        return Err(QueryError::InvalidCharacter(c));
+    }
+    Ok(Charset::from_str(&letters))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_query_pattern() {
+        let query = Query::new().pattern("a?e??").unwrap();
+        assert!(query.matches(Word::new("abets")));
+        assert!(query.matches(Word::new("agent")));
+        assert!(!query.matches(Word::new("about")));
+        assert!(!query.matches(Word::new("bread")));
+
+        assert_eq!(
+            Query::new().pattern("a?e?").err(),
+            Some(QueryError::PatternLength)
+        );
+        assert_eq!(
+            Query::new().pattern("a?e?1").err(),
+            Some(QueryError::InvalidCharacter('1'))
+        );
+    }
+
+    #[test]
+    fn test_query_error_message() {
+        let err = Query::new().pattern("a1e??").unwrap_err();
+        assert_eq!(err.message(Locale::En), "Invalid character '1'");
+        assert_eq!(err.message(Locale::Sv), "Ogiltigt tecken '1'");
+    }
+
+    #[test]
+    fn test_query_letters() {
+        let query = Query::new().contains("rs").unwrap().excludes("t").unwrap();
+        assert!(query.matches(Word::new("sorry")));
+        assert!(!query.matches(Word::new("roast")));
+        assert!(!query.matches(Word::new("round")));
+    }
+
+    #[test]
+    fn test_query_counts() {
+        let query = Query::new().counts("e2, s0-1").unwrap();
+        assert!(query.matches(Word::new("sheep")));
+        assert!(query.matches(Word::new("eaten")));
+        assert!(query.matches(Word::new("tease")));
+        assert!(!query.matches(Word::new("geese")));
+        assert!(!query.matches(Word::new("esses")));
+        assert!(!query.matches(Word::new("about")));
+
+        assert_eq!(
+            Query::new().counts("e").err(),
+            Some(QueryError::InvalidCount)
+        );
+        assert_eq!(
+            Query::new().counts("e2-1").err(),
+            Some(QueryError::InvalidCount)
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use crate::dictionary;
+use crate::feedback;
+use crate::state::{GameState, Mode, Phase};
+use crate::theme::Theme;
+
+// Builds the spoiler free text grid for a finished game, e.g. "Wordlx 1234 3/6*"
+// followed by one row of tiles per guess in the colors of the theme. Daily games are numbered,
+// other games are dated.
+pub fn share_text(state: &GameState, theme: Theme) -> String {
+    let game = match state.mode {
+        Mode::Daily => state.day.to_string(),
+        _ => dictionary::date(state.day),
+    };
+    let score = match state.phase {
+        Phase::Won => state.guesses.len().to_string(),
+        _ => "X".to_string(),
+    };
+    let hard = if state.hard { "*" } else { "" };
+
+    let mut text = format!("Wordlx {} {}/6{}\n", game, score, hard);
+    for guess in &state.guesses {
+        text.push('\n');
+        text.extend(
+            feedback::score(*guess, state.answer)
+                .iter()
+                .map(|result| theme.tile(*result)),
+        );
+    }
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_share_text() {
+        let mut state = GameState::new("crane");
+        state.mode = Mode::Daily;
+        state.day = 1234;
+        state.hard = true;
+        state.guess("trace").unwrap();
+        state.guess("crane").unwrap();
+
+        let text = share_text(&state, Theme::Dark);
+        assert_eq!(text, "Wordlx 1234 2/6*\n\n⬛🟩🟩🟨🟩\n🟩🟩🟩🟩🟩");
+
+        let text = share_text(&state, Theme::Light);
+        assert_eq!(text, "Wordlx 1234 2/6*\n\n⬜🟩🟩🟨🟩\n🟩🟩🟩🟩🟩");
+
+        let text = share_text(&state, Theme::Contrast);
+        assert_eq!(text, "Wordlx 1234 2/6*\n\n⬛🟧🟧🟦🟧\n🟧🟧🟧🟧🟧");
+    }
+
+    #[test]
+    fn test_share_text_lost() {
+        let mut state = GameState::new("crane");
+        state.day = 0;
+        for _ in 0..6 {
+            state.guess("stomp").unwrap();
+        }
+        let text = share_text(&state, Theme::Dark);
+        assert!(text.starts_with("Wordlx 2021-06-19 X/6\n"));
+        assert_eq!(text.lines().count(), 8);
+    }
+}
@@ -0,0 +1,309 @@
+use lru::LruCache;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+use crate::dictionary::{Dictionary, WORDS};
+use crate::feedback;
+use crate::frequency::LetterStats;
+use crate::metrics;
+use crate::stats::{Constraints, WordFilter};
+use crate::word::Word;
+
+// Choices scored so far, lowest expected remaining candidates first
+#[derive(Debug, Clone)]
+pub struct Ranking {
+    pub scored: Vec<(Word, f64)>,
+    // false if scoring was stopped before every choice was scored
+    pub complete: bool,
+}
+
+// Scores each choice by the expected number of candidates remaining after guessing it, lowest first.
+// The choices are the candidates, each with a prior probability of being the answer taken from
+// the dictionary word frequencies. Guessing a choice splits the candidates by the feedback each
+// would give, and the score sums, over every candidate, its probability times the weight of the
+// candidates giving the same feedback. Without frequencies this is the plain expected count.
+// Scoring stops as soon as `stop` returns true. Choices are scored in order of how informative
+// their letters are, so a partial ranking has usually found the best guesses already.
+pub fn rank(dict: &Dictionary, choices: &[Word], stop: impl Fn() -> bool) -> Ranking {
+    let letters = LetterStats::new(choices);
+    let information = |word: &Word| -> f64 {
+        let set = word.charset();
+        ('a'..='z')
+            .filter(|c| set.includes(*c))
+            .map(|c| letters.information(c))
+            .sum()
+    };
+    let mut ordered = choices.to_vec();
+    ordered.sort_by(|a, b| information(b).total_cmp(&information(a)));
+
+    let weights: Vec<f64> = choices.iter().map(|w| dict.weight(*w)).collect();
+    let total: f64 = weights.iter().sum();
+    // weight of the candidates giving each of the 3^5 feedback patterns
+    let mut patterns = [0.0; 243];
+    let mut scored = Vec::with_capacity(choices.len());
+    for choice in ordered {
+        if stop() {
+            break;
+        }
+        patterns.fill(0.0);
+        for (answer, weight) in choices.iter().zip(&weights) {
+            let pattern = feedback::score(choice, *answer)
+                .iter()
+                .fold(0, |pattern, tile| pattern * 3 + *tile as usize);
+            patterns[pattern] += weight;
+        }
+        // the chance of each pattern times the weight it leaves, as a share of all candidates
+        let remaining: f64 = patterns.iter().map(|weight| weight * weight).sum::<f64>() / total;
+        scored.push((choice, remaining / total * choices.len() as f64));
+    }
+
+    let complete = scored.len() == choices.len();
+    scored.sort_by(|a, b| a.1.total_cmp(&b.1));
+    Ranking { scored, complete }
+}
+
+// Sets the flag when dropped, which happens when the request waiting on the solver goes away
+struct Cancel(Arc<AtomicBool>);
+
+impl Drop for Cancel {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+// Complete rankings by the constraints they were computed for. Memory is bounded by the total
+// number of ranked words kept, evicting the least recently used rankings first. Every ranking
+// counts as at least one word, so empty rankings can't pile up without bound.
+#[derive(Debug)]
+struct Cache {
+    rankings: LruCache<Constraints, Arc<Ranking>>,
+    words: usize,
+    capacity: usize,
+}
+
+impl Cache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            rankings: LruCache::unbounded(),
+            words: 0,
+            capacity,
+        }
+    }
+
+    fn get(&mut self, key: &Constraints) -> Option<Arc<Ranking>> {
+        self.rankings.get(key).cloned()
+    }
+
+    fn weight(ranking: &Ranking) -> usize {
+        ranking.scored.len().max(1)
+    }
+
+    fn insert(&mut self, key: Constraints, ranking: Arc<Ranking>) {
+        let words = Self::weight(&ranking);
+        if self.capacity == 0 || words > self.capacity {
+            return;
+        }
+        if let Some(old) = self.rankings.put(key, ranking) {
+            self.words -= Self::weight(&old);
+        }
+        self.words += words;
+        while self.words > self.capacity {
+            let Some((_, evicted)) = self.rankings.pop_lru() else {
+                break;
+            };
+            self.words -= Self::weight(&evicted);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.rankings.clear();
+        self.words = 0;
+    }
+}
+
+// Runs rankings on the blocking thread pool, a bounded number at a time, so solving never
+// holds up the async workers serving other requests
+#[derive(Debug)]
+pub struct Solver {
+    permits: Arc<Semaphore>,
+    budget: Duration,
+    cache: Mutex<Cache>,
+}
+
+impl Solver {
+    // Keeps rankings of up to `cache` words in total, or none at all if it is zero
+    pub fn new(workers: usize, budget: Duration, cache: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(workers)),
+            budget,
+            cache: Mutex::new(Cache::new(cache)),
+        }
+    }
+
+    // Forgets every cached ranking, for when the answer words change
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    // Ranks the choices matching the filter within the time budget. Time spent waiting for a
+    // free worker counts towards the budget, and None is returned if none frees up in time.
+    // The choices must be the answer words matching the filter, which is what makes the
+    // filter constraints a valid cache key.
+    pub async fn rank(&self, filter: WordFilter, choices: Vec<Word>) -> Option<Arc<Ranking>> {
+        let key = filter.constraints();
+        if let Some(ranking) = self.cache.lock().unwrap().get(&key) {
+            metrics::solver_cache(true);
+            return Some(ranking);
+        }
+        metrics::solver_cache(false);
+
+        let deadline = Instant::now() + self.budget;
+        let permit = tokio::time::timeout_at(deadline.into(), self.permits.clone().acquire_owned())
+            .await
+            .ok()?
+            .expect("solver semaphore closed");
+
+        let cancel = Cancel(Arc::new(AtomicBool::new(false)));
+        let cancelled = cancel.0.clone();
+        let ranking = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            rank(&WORDS, &choices, || {
+                cancelled.load(Ordering::Relaxed) || Instant::now() >= deadline
+            })
+        })
+        .await
+        .expect("solver panicked");
+        drop(cancel);
+
+        let ranking = Arc::new(ranking);
+        // partial rankings depend on how busy the server was, so only complete ones are kept
+        if ranking.complete {
+            self.cache.lock().unwrap().insert(key, ranking.clone());
+        }
+        Some(ranking)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rank() {
+        let choices: Vec<Word> = WORDS.iter().take(50).copied().collect();
+
+        let full = rank(&WORDS, &choices, || false);
+        assert!(full.complete);
+        assert_eq!(full.scored.len(), choices.len());
+        assert!(full.scored.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+
+        let stopped = rank(&WORDS, &choices, || true);
+        assert!(!stopped.complete);
+        assert!(stopped.scored.is_empty());
+    }
+
+    #[test]
+    fn test_rank_expected_remaining() {
+        let words = ["hatch", "match", "latch"];
+        let choices: Vec<Word> = words.iter().map(|w| Word::new(w)).collect();
+        let score = |ranking: &Ranking, word: &str| {
+            ranking
+                .scored
+                .iter()
+                .find(|(w, _)| *w == Word::new(word))
+                .unwrap()
+                .1
+        };
+
+        // each guess is either right or leaves the other two, (1 + 2 * 2) / 3 expected
+        let mut dict = Dictionary::parse(&words.join("\n"));
+        let ranking = rank(&dict, &choices, || false);
+        for word in words {
+            assert!((score(&ranking, word) - 5.0 / 3.0).abs() < 1e-9);
+        }
+
+        // a likely answer is more likely to be right, so it leaves fewer candidates
+        dict.load_weights("hatch 8\nmatch 1\nlatch 1\n");
+        let ranking = rank(&dict, &choices, || false);
+        assert_eq!(ranking.scored[0].0, Word::new("hatch"));
+        assert!((score(&ranking, "hatch") - 2.04).abs() < 1e-9);
+        assert!((score(&ranking, "match") - 2.46).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_solver_budget() {
+        let filter = WordFilter::new(Word::new("crane"));
+        let choices: Vec<Word> = WORDS.iter().copied().collect();
+        let solver = Solver::new(1, Duration::ZERO, 0);
+        let ranking = solver.rank(filter, choices).await;
+        // no time at all either finds no worker or scores nothing
+        assert!(ranking.is_none_or(|ranking| !ranking.complete));
+    }
+
+    #[tokio::test]
+    async fn test_solver_cache() {
+        let mut filter = WordFilter::new(Word::new("crane"));
+        filter.apply(Word::new("slate"));
+        let choices: Vec<Word> = WORDS
+            .iter()
+            .copied()
+            .filter(|w| filter.matches(*w))
+            .collect();
+        let solver = Solver::new(1, Duration::from_secs(60), choices.len());
+
+        let first = solver.rank(filter.clone(), choices.clone()).await.unwrap();
+        let second = solver.rank(filter, choices).await.unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        solver.clear_cache();
+        assert!(solver.cache.lock().unwrap().rankings.is_empty());
+    }
+
+    #[test]
+    fn test_cache_eviction() {
+        let ranking = |words: &[&str]| {
+            Arc::new(Ranking {
+                scored: words.iter().map(|w| (Word::new(w), 0.0)).collect(),
+                complete: true,
+            })
+        };
+        let key = |guess: &str| {
+            let mut filter = WordFilter::new(Word::new("crane"));
+            filter.apply(Word::new(guess));
+            filter.constraints()
+        };
+
+        let mut cache = Cache::new(4);
+        cache.insert(key("slate"), ranking(&["crane", "crank"]));
+        cache.insert(key("hello"), ranking(&["crane"]));
+        assert!(cache.get(&key("slate")).is_some());
+
+        // the least recently used ranking makes room for the new one
+        cache.insert(key("dough"), ranking(&["crane", "brine"]));
+        assert_eq!(cache.words, 4);
+        assert!(cache.get(&key("slate")).is_some());
+        assert!(cache.get(&key("hello")).is_none());
+        assert!(cache.get(&key("dough")).is_some());
+
+        // rankings larger than the whole cache are not kept
+        cache.insert(
+            key("tried"),
+            ranking(&["crane", "crank", "brine", "drink", "drank"]),
+        );
+        assert!(cache.get(&key("tried")).is_none());
+
+        // empty rankings take up room too
+        for guess in ["pious", "dumpy", "wight", "fjord", "lymph"] {
+            cache.insert(key(guess), ranking(&[]));
+        }
+        assert_eq!(cache.rankings.len(), 4);
+        assert_eq!(cache.words, 4);
+
+        // a cache without capacity keeps nothing
+        let mut cache = Cache::new(0);
+        cache.insert(key("slate"), ranking(&[]));
+        assert!(cache.get(&key("slate")).is_none());
+    }
+}
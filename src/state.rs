@@ -1,15 +1,58 @@
 use serde::{Deserialize, Serialize};
 
-use crate::dictionary::{GUESSES, WORDS};
+use crate::dictionary::{self, GUESSES, WORDS};
+use crate::feedback::{self, Feedback};
 use crate::word::Word;
 
 #[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Phase {
+    // sessions saved before the API was added use the capitalized names
+    #[serde(alias = "Playing")]
     Playing,
+    #[serde(alias = "Won")]
     Won,
+    #[serde(alias = "Lost")]
     Lost,
 }
 
+#[derive(PartialEq, Copy, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    #[default]
+    #[serde(alias = "Normal")]
+    Normal,
+    // Answers are biased towards common words
+    #[serde(alias = "Easy")]
+    Easy,
+    // Everyone gets the same answer each day
+    #[serde(alias = "Daily")]
+    Daily,
+}
+
+impl Mode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Mode::Normal => "normal",
+            Mode::Easy => "easy",
+            Mode::Daily => "daily",
+        }
+    }
+}
+
+impl std::str::FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(Mode::Normal),
+            "easy" => Ok(Mode::Easy),
+            "daily" => Ok(Mode::Daily),
+            _ => Err(format!("unknown game mode '{}'", s)),
+        }
+    }
+}
+
 #[derive(PartialEq, Debug, Serialize, Deserialize)]
 pub enum Input {
     Character(char),
@@ -21,15 +64,41 @@ pub enum Input {
 pub enum Error {
     None,
     InvalidGuess,
+    WrongLength,
+    InvalidCharacter,
+    GameOver,
+    HardMode,
+}
+
+impl Error {
+    // The key of the message in the user interface catalogs
+    pub fn key(&self) -> &'static str {
+        match self {
+            Error::None => "",
+            Error::InvalidGuess => "error_invalid_guess",
+            Error::WrongLength => "error_wrong_length",
+            Error::InvalidCharacter => "error_invalid_character",
+            Error::GameOver => "error_game_over",
+            Error::HardMode => "error_hard_mode",
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameState {
+    #[serde(default)]
+    pub mode: Mode,
     pub phase: Phase,
     pub answer: Word,
     pub guess: Guess,
     pub guesses: Vec<Word>,
     pub error: Error,
+    // revealed hints must be used in later guesses
+    #[serde(default)]
+    pub hard: bool,
+    // the day the game was started, as counted by dictionary::today
+    #[serde(default)]
+    pub day: u32,
 }
 
 impl GameState {
@@ -41,16 +110,30 @@ impl GameState {
         let answer = Word::new(answer);
         Self {
             answer,
+            mode: Mode::Normal,
             phase: Phase::Playing,
             error: Error::None,
             guesses: Vec::new(),
             guess: Guess::new(),
+            hard: false,
+            day: dictionary::today(),
         }
     }
 
-    pub fn new_random() -> Self {
-        let answer = WORDS.random();
-        Self::new(&answer.to_string())
+    pub fn start(mode: Mode, answer: Word) -> Self {
+        Self {
+            mode,
+            ..Self::new(&answer.to_string())
+        }
+    }
+
+    pub fn new_random(mode: Mode) -> Self {
+        let answer = match mode {
+            Mode::Normal => WORDS.random(),
+            Mode::Easy => WORDS.random_common(),
+            Mode::Daily => WORDS.daily(dictionary::today()),
+        };
+        Self::start(mode, answer)
     }
 
     pub fn input(&mut self, input: Input) {
@@ -65,6 +148,43 @@ impl GameState {
         }
     }
 
+    // Submits a complete word in one go, replacing anything typed so far
+    pub fn guess(&mut self, word: &str) -> Result<(), Error> {
+        if self.phase != Phase::Playing {
+            return Err(Error::GameOver);
+        }
+        let word = word.trim().to_lowercase();
+        self.error = if word.chars().count() != 5 {
+            Error::WrongLength
+        } else if !word.chars().all(|c| c.is_ascii_lowercase()) {
+            Error::InvalidCharacter
+        } else {
+            self.error = Error::None;
+            self.guess.clear();
+            word.chars().for_each(|c| self.guess.put(c));
+            self.submit();
+            self.error
+        };
+
+        match self.error {
+            Error::None => Ok(()),
+            error => Err(error),
+        }
+    }
+
+    // Returns true if the word keeps every correct letter in place and includes every present letter
+    fn uses_hints(&self, word: Word) -> bool {
+        self.guesses.iter().all(|guess| {
+            feedback::score(*guess, self.answer).iter().enumerate().all(
+                |(i, result)| match result {
+                    Feedback::Correct => word.at(i) == guess.at(i),
+                    Feedback::Present => word.contains(guess.at(i)),
+                    Feedback::Absent => true,
+                },
+            )
+        })
+    }
+
     pub fn full(&self) -> bool {
         self.guesses.len() == 6
     }
@@ -82,10 +202,15 @@ impl GameState {
         }
 
         let guess: Word = self.guess.clone().into();
+        if self.hard && !self.uses_hints(guess) {
+            self.error = Error::HardMode;
+            return;
+        }
+
         self.guess.clear();
-        self.guesses.push(guess.clone());
+        self.guesses.push(guess);
 
-        if guess == self.answer.into() {
+        if guess == self.answer {
             self.phase = Phase::Won;
         } else if self.full() {
             self.phase = Phase::Lost;
@@ -95,7 +220,7 @@ impl GameState {
 
 impl Default for GameState {
     fn default() -> Self {
-        Self::new_random()
+        Self::new_random(Mode::Normal)
     }
 }
 
@@ -147,3 +272,60 @@ impl From<Guess> for Word {
         word
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_serde_names() {
+        assert_eq!(serde_json::to_string(&Phase::Won).unwrap(), "\"won\"");
+        let phase: Phase = serde_json::from_str("\"Playing\"").unwrap();
+        assert_eq!(phase, Phase::Playing);
+        let mode: Mode = serde_json::from_str("\"Daily\"").unwrap();
+        assert_eq!(mode, Mode::Daily);
+    }
+
+    #[test]
+    fn test_guess() {
+        let mut state = GameState::new("crane");
+        assert_eq!(state.guess("react"), Ok(()));
+        assert_eq!(state.guesses, vec![Word::new("react")]);
+        assert_eq!(state.phase, Phase::Playing);
+
+        assert_eq!(state.guess(" CRANE "), Ok(()));
+        assert_eq!(state.phase, Phase::Won);
+        assert_eq!(state.guess("crane"), Err(Error::GameOver));
+    }
+
+    #[test]
+    fn test_guess_invalid() {
+        let mut state = GameState::new("crane");
+        assert_eq!(state.guess(""), Err(Error::WrongLength));
+        assert_eq!(state.guess("cranes"), Err(Error::WrongLength));
+        assert_eq!(state.guess("cr4ne"), Err(Error::InvalidCharacter));
+        assert_eq!(state.guess("zzzzz"), Err(Error::InvalidGuess));
+        assert_eq!(state.error, Error::InvalidGuess);
+        assert!(state.guesses.is_empty());
+    }
+
+    #[test]
+    fn test_guess_hard_mode() {
+        let mut state = GameState::new("crane");
+        state.hard = true;
+        assert_eq!(state.guess("trace"), Ok(()));
+        // r, a and e are known, c is present
+        assert_eq!(state.guess("react"), Err(Error::HardMode));
+        assert_eq!(state.guess("brake"), Err(Error::HardMode));
+        assert_eq!(state.guess("crane"), Ok(()));
+    }
+
+    #[test]
+    fn test_guess_lost() {
+        let mut state = GameState::new("crane");
+        for _ in 0..6 {
+            assert_eq!(state.guess("react"), Ok(()));
+        }
+        assert_eq!(state.phase, Phase::Lost);
+    }
+}
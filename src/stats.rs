@@ -1,7 +1,9 @@
+use tracing::{instrument, trace};
+
 use crate::charset::Charset;
 use crate::word::Word;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct WordSpace([Charset; 5]);
 
 impl WordSpace {
@@ -17,11 +19,25 @@ impl WordSpace {
         self.0[i] = Charset::none().include(c);
     }
 
+    // Letters allowed in position i
+    pub fn at(&self, i: usize) -> Charset {
+        self.0[i]
+    }
+
     pub fn matches(&self, word: Word) -> bool {
         word.iter().enumerate().all(|(i, c)| self.0[i].includes(c))
     }
 }
 
+// Everything a filter matches on, without the answer. Filters with equal constraints
+// match the same words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Constraints {
+    rejected: Charset,
+    required: Charset,
+    space: WordSpace,
+}
+
 #[derive(Debug, Clone)]
 pub struct WordFilter {
     answer: Word,
@@ -43,22 +59,23 @@ impl WordFilter {
         }
     }
 
+    #[instrument(level = "trace", skip(self), fields(%guess))]
     pub fn apply(&mut self, guess: Word) {
         // update mask
         for (i, c) in guess.iter().enumerate() {
             if c == self.answer.at(i) {
                 // correct character in correct position
-                println!("position {} is {}", i, c);
+                trace!(position = i, letter = %c, "correct");
                 self.correct.set(i, c);
                 self.required.include(c);
                 self.space.only(i, c);
             } else if self.answer.contains(c) {
-                println!("requires {} since it exists in {}", c, self.answer);
+                trace!(position = i, letter = %c, "present");
                 // correct character in wrong position
                 self.required.include(c);
                 self.space.exclude(i, c);
             } else {
-                println!("rejecting {}", c);
+                trace!(letter = %c, "absent");
                 // incorrect character
                 self.rejected.include(c);
                 for i in 0..5 {
@@ -69,6 +86,7 @@ impl WordFilter {
     }
 
     // Rejects all characters in a word, unless they are already required
+    #[allow(dead_code)]
     pub fn reject(&mut self, word: Word) {
         for c in &word {
             if !self.required.includes(c) {
@@ -77,6 +95,14 @@ impl WordFilter {
         }
     }
 
+    pub fn constraints(&self) -> Constraints {
+        Constraints {
+            rejected: self.rejected,
+            required: self.required,
+            space: self.space,
+        }
+    }
+
     pub fn matches(&self, word: Word) -> bool {
         let wm = word.charset();
 
@@ -149,4 +175,18 @@ mod test {
         assert_eq!(filter.matches(Word::new("steal")), false);
         assert_eq!(filter.matches(Word::new("steak")), false);
     }
+
+    #[test]
+    fn test_constraints() {
+        // the same feedback from different answers gives the same constraints
+        let mut crank = WordFilter::new(Word::new("crank"));
+        let mut drank = WordFilter::new(Word::new("drank"));
+        crank.apply(Word::new("hello"));
+        drank.apply(Word::new("hello"));
+        assert_eq!(crank.constraints(), drank.constraints());
+
+        crank.apply(Word::new("cramp"));
+        drank.apply(Word::new("cramp"));
+        assert_ne!(crank.constraints(), drank.constraints());
+    }
 }
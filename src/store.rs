@@ -0,0 +1,293 @@
+use async_trait::async_trait;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::io::AsyncWriteExt;
+use tower_sessions::{
+    cookie::time::OffsetDateTime,
+    session::{Id, Record},
+    session_store::{self, Error, ExpiredDeletion},
+    MemoryStore, SessionStore,
+};
+use tracing::{error, warn};
+
+use crate::metrics;
+
+// Number of temporary files written, for naming the next one
+static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
+
+// Keeps each session as a JSON file in a directory, so games and stats survive restarts
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    // Makes sure renamed session files have reached the disk
+    pub fn sync(&self) -> std::io::Result<()> {
+        std::fs::File::open(&self.dir)?.sync_all()
+    }
+
+    fn path(&self, id: &Id) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    async fn read(&self, path: &PathBuf) -> session_store::Result<Option<Record>> {
+        let data = match tokio::fs::read(path).await {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::Backend(err.to_string())),
+        };
+        let record = serde_json::from_slice(&data).map_err(|err| Error::Decode(err.to_string()))?;
+        Ok(Some(record))
+    }
+}
+
+#[async_trait]
+impl SessionStore for FileStore {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        // session id collision mitigation
+        while tokio::fs::try_exists(self.path(&record.id))
+            .await
+            .map_err(|err| Error::Backend(err.to_string()))?
+        {
+            record.id = Id::default();
+        }
+        self.save(record).await
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        let data = serde_json::to_vec(record).map_err(|err| Error::Encode(err.to_string()))?;
+        // write to a temporary file first so a crash never leaves a half written session,
+        // one per save so concurrent saves of the same session don't write over each other
+        let n = TEMP_FILES.fetch_add(1, Ordering::Relaxed);
+        let tmp = self.dir.join(format!("{}.{}.tmp", record.id, n));
+        let result = async {
+            let mut file = tokio::fs::File::create(&tmp).await?;
+            file.write_all(&data).await?;
+            file.sync_all().await?;
+            tokio::fs::rename(&tmp, self.path(&record.id)).await
+        }
+        .await;
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&tmp).await;
+        }
+        result.map_err(|err| Error::Backend(err.to_string()))
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        // a session that can not be decoded is treated as missing, so the player gets a new one
+        let record = match self.read(&self.path(session_id)).await {
+            Err(Error::Decode(err)) => {
+                warn!(%err, "discarding unreadable session");
+                None
+            }
+            result => result?,
+        };
+        Ok(record.filter(|record| record.expiry_date > OffsetDateTime::now_utc()))
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        match tokio::fs::remove_file(self.path(session_id)).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(Error::Backend(err.to_string())),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl ExpiredDeletion for FileStore {
+    async fn delete_expired(&self) -> session_store::Result<()> {
+        let mut entries = tokio::fs::read_dir(&self.dir)
+            .await
+            .map_err(|err| Error::Backend(err.to_string()))?;
+        let now = OffsetDateTime::now_utc();
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|err| Error::Backend(err.to_string()))?
+        {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            // unreadable sessions are removed along with expired ones
+            let expired = match self.read(&path).await {
+                Ok(Some(record)) => record.expiry_date <= now,
+                _ => true,
+            };
+            if expired {
+                let _ = tokio::fs::remove_file(&path).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The session store selected at startup
+#[derive(Debug, Clone)]
+pub enum Store {
+    Memory(MemoryStore),
+    File(FileStore),
+}
+
+impl Store {
+    // Removes expired sessions at the given interval, forever
+    pub async fn delete_expired_every(self, period: std::time::Duration) {
+        let Store::File(store) = self else {
+            // the memory store drops expired sessions on load
+            return;
+        };
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            if let Err(err) = store.delete_expired().await {
+                error!(%err, "failed to delete expired sessions");
+            }
+        }
+    }
+
+    // Saves a player record next to the sessions. Records are not counted as active sessions.
+    pub async fn save_player(&self, record: &Record) -> session_store::Result<()> {
+        match self {
+            Store::Memory(store) => store.save(record).await,
+            Store::File(store) => store.save(record).await,
+        }
+    }
+
+    // Flushes sessions to disk before shutting down. Every session file is synced when it is
+    // saved, so once in-flight requests are drained only the renames in the directory remain.
+    pub fn close(&self) -> std::io::Result<()> {
+        match self {
+            Store::Memory(_) => Ok(()),
+            Store::File(store) => store.sync(),
+        }
+    }
+}
+
+#[async_trait]
+impl SessionStore for Store {
+    async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        match self {
+            Store::Memory(store) => store.create(record).await,
+            Store::File(store) => store.create(record).await,
+        }?;
+        metrics::session_saved(record.id, record.expiry_date);
+        Ok(())
+    }
+
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        match self {
+            Store::Memory(store) => store.save(record).await,
+            Store::File(store) => store.save(record).await,
+        }?;
+        metrics::session_saved(record.id, record.expiry_date);
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
+        match self {
+            Store::Memory(store) => store.load(session_id).await,
+            Store::File(store) => store.load(session_id).await,
+        }
+    }
+
+    async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
+        metrics::session_deleted(session_id);
+        match self {
+            Store::Memory(store) => store.delete(session_id).await,
+            Store::File(store) => store.delete(session_id).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tower_sessions::cookie::time::Duration;
+
+    fn record(expiry: Duration) -> Record {
+        Record {
+            id: Id::default(),
+            data: Default::default(),
+            expiry_date: OffsetDateTime::now_utc() + expiry,
+        }
+    }
+
+    fn temp_store(name: &str) -> FileStore {
+        let dir = std::env::temp_dir().join(format!("wordlx-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        FileStore::new(dir).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_file_store() {
+        let store = temp_store("store");
+        let mut record = record(Duration::hours(1));
+        record
+            .data
+            .insert("game".to_string(), serde_json::json!({ "guesses": 2 }));
+        store.create(&mut record).await.unwrap();
+
+        // a new store over the same directory sees the session, as after a restart
+        let reopened = FileStore::new(store.dir.clone()).unwrap();
+        assert_eq!(
+            reopened.load(&record.id).await.unwrap(),
+            Some(record.clone())
+        );
+
+        reopened.delete(&record.id).await.unwrap();
+        assert_eq!(store.load(&record.id).await.unwrap(), None);
+        let _ = std::fs::remove_dir_all(&store.dir);
+    }
+
+    #[tokio::test]
+    async fn test_file_store_concurrent_saves() {
+        let store = temp_store("concurrent");
+        let mut record = record(Duration::hours(1));
+        store.create(&mut record).await.unwrap();
+        let saves = (0..20).map(|i| {
+            let store = store.clone();
+            let mut record = record.clone();
+            record.data.insert("n".to_string(), serde_json::json!(i));
+            tokio::spawn(async move { store.save(&record).await })
+        });
+        for save in saves.collect::<Vec<_>>() {
+            save.await.unwrap().unwrap();
+        }
+        assert!(store.load(&record.id).await.unwrap().is_some());
+        // no temporary files are left behind
+        assert_eq!(std::fs::read_dir(&store.dir).unwrap().count(), 1);
+        let _ = std::fs::remove_dir_all(&store.dir);
+    }
+
+    #[tokio::test]
+    async fn test_file_store_expired() {
+        let store = temp_store("expired");
+        let mut active = record(Duration::hours(1));
+        let mut expired = record(Duration::hours(-1));
+        store.create(&mut active).await.unwrap();
+        store.create(&mut expired).await.unwrap();
+        assert_eq!(store.load(&expired.id).await.unwrap(), None);
+
+        store.delete_expired().await.unwrap();
+        assert!(store.path(&active.id).exists());
+        assert!(!store.path(&expired.id).exists());
+        let _ = std::fs::remove_dir_all(&store.dir);
+    }
+
+    #[tokio::test]
+    async fn test_file_store_unreadable() {
+        let store = temp_store("unreadable");
+        let id = Id::default();
+        std::fs::write(store.path(&id), "{ not json").unwrap();
+        assert_eq!(store.load(&id).await.unwrap(), None);
+        let _ = std::fs::remove_dir_all(&store.dir);
+    }
+}
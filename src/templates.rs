@@ -1,26 +1,46 @@
-use maud::{html, Markup};
+use maud::{html, Markup, PreEscaped};
 
 use crate::{
+    assets,
     charset::Charset,
-    state::{Error, GameState, Phase},
+    feedback::{self, Feedback},
+    frequency::{self, LetterStats},
+    i18n::Locale,
+    layout::Layout,
+    player::{Settings, Stats},
+    state::{Error, GameState, Mode, Phase},
+    theme::Theme,
     word::Word,
 };
 
-pub fn page(title: &str, content: Markup) -> Markup {
+// Montserrat in the weights the stylesheet uses
+const FONTS: [(&str, u16); 2] = [
+    ("fonts/Montserrat-Regular.ttf", 400),
+    ("fonts/Montserrat-Bold.ttf", 700),
+];
+
+pub fn page(title: &str, theme: Theme, lang: Locale, content: Markup) -> Markup {
     html! {
         (maud::DOCTYPE)
-        html lang="en" {
+        html lang=(lang.as_str()) {
             head {
                 meta charset="UTF-8";
                 meta name="viewport" content="width=device-width, initial-scale=1.0";
                 title { (title) }
-                script src="https://unpkg.com/htmx.org@1.9.12" {}
-                link rel="stylesheet" href="assets/style.css";
-                link rel="preconnect" href="https://fonts.googleapis.com";
-                link rel="preconnect" href="https://fonts.gstatic.com" crossorigin;
-                link href="https://fonts.googleapis.com/css2?family=Montserrat:wght@400..700&display=swap" rel="stylesheet";
+                script src=[assets::url("htmx.min.js")] {}
+                style {
+                    @for (name, weight) in FONTS {
+                        @if let Some(font) = assets::url(name) {
+                            (PreEscaped(format!(
+                                "@font-face {{ font-family: \"Montserrat\"; font-weight: {}; src: url(\"{}\") format(\"truetype\"); }}",
+                                weight, font
+                            )))
+                        }
+                    }
+                }
+                link rel="stylesheet" href=[assets::url("style.css")];
             }
-            body {
+            body class=(theme.as_str()) {
                 div.content {
                     (content)
                 }
@@ -29,68 +49,425 @@ pub fn page(title: &str, content: Markup) -> Markup {
     }
 }
 
-pub fn game_board(state: &GameState) -> Markup {
-    let required = state.answer.charset();
+// Renders the board. Stats are passed when the game just finished, to show them in a modal.
+pub fn game_board(
+    state: &GameState,
+    stats: Option<&Stats>,
+    layout: Layout,
+    lang: Locale,
+) -> Markup {
     html! {
         div id="game" {
-            (guess_table(html! {
+            @if let Some(stats) = stats {
+                @let last = (state.phase == Phase::Won).then_some(state.guesses.len());
+                (stats_modal(stats, last, lang))
+            }
+            (guess_table(lang.text("guesses"), html! {
                 @for guess in &state.guesses {
-                    (guess_row(guess.clone(), state.answer, required, true))
+                    (scored_row(*guess, state.answer, lang))
                 }
                 @if !state.full() {
-                    (guess_row(state.guess.clone().into(), Word::empty(), Charset::none(), false))
+                    (guess_row(state.guess.clone().into(), Word::empty(), Charset::none(), false, lang))
                     @for _ in 0..5 - state.guesses.len() {
-                        (guess_row(Word::empty(), Word::empty(), Charset::none(), false))
+                        (guess_row(Word::empty(), Word::empty(), Charset::none(), false, lang))
                     }
                 }
             }))
             div.panel {
-                @match state.error {
-                    Error::None => {},
-                    Error::InvalidGuess => p.message.error { "Invalid guess" },
+                @if state.error != Error::None {
+                    p.message.error { (lang.text(state.error.key())) }
                 }
                 @match state.phase {
                     Phase::Won => {
-                        p.message { "You won!" }
+                        p.message { (lang.text("won")) }
                     },
                     Phase::Lost => {
-                        p.message { "You lost!" }
-                        p.message.small { "The answer was " span.word { (state.answer) } }
+                        p.message { (lang.text("lost")) }
+                        p.message.small { (lang.format("answer_was", &[("answer", &uppercase(state.answer))])) }
                     },
                     _ => {},
                 }
                 @if state.phase != Phase::Playing {
-                    button hx-post="/api/reset" hx-target="#game" hx-swap="outerHTML" { "Play again" }
+                    // there is only one daily game, so continue with a normal one
+                    @let mode = if state.mode == Mode::Daily { Mode::Normal } else { state.mode };
+                    (reset_button(mode, lang.text("play_again")))
+                    button hx-get="/share" hx-target="#share" hx-swap="outerHTML" { (lang.text("share")) }
+                }
+            }
+            div #share {}
+            @if state.phase == Phase::Playing {
+                (keyboard(state, layout, lang, false))
+            }
+        }
+    }
+}
+
+// An on-screen keyboard showing the best known state of every guessed letter.
+// Keys post to /api/input just like the hardware keyboard does. With `oob` set it
+// replaces the keyboard on the page from a response meant for another element.
+pub fn keyboard(state: &GameState, layout: Layout, lang: Locale, oob: bool) -> Markup {
+    let states = feedback::letter_states(&state.guesses, state.answer);
+    let key = |value: &str, label: Markup, class: &str, name: &str| {
+        let vals = format!(r#"{{"key": "{}"}}"#, value);
+        html! {
+            button.key.(class) type="button" aria-label=(name) hx-post="/api/input" hx-vals=(vals) hx-target="#game" hx-swap="outerHTML" {
+                (label)
+            }
+        }
+    };
+    html! {
+        div #keyboard .keyboard role="group" aria-label=(lang.text("keyboard")) hx-swap-oob=[oob.then_some("true")] {
+            @for (i, row) in layout.rows().iter().enumerate() {
+                div.row {
+                    @if i == 2 {
+                        (key("enter", html! { (lang.text("enter")) }, "wide", lang.text("enter")))
+                    }
+                    @for c in row.chars() {
+                        @let state = states[c as usize - 'a' as usize];
+                        @let class = state.map_or("", |s| s.as_str());
+                        (key(&c.to_string(), html! { (c) }, class, &letter_label(c, state, lang)))
+                    }
+                    @if i == 2 {
+                        (key("backspace", html! { "⌫" }, "wide", lang.text("backspace")))
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn uppercase(word: Word) -> String {
+    word.to_string().to_uppercase()
+}
+
+// How a letter result is read out by screen readers
+fn describe(result: Feedback, lang: Locale) -> &'static str {
+    lang.text(match result {
+        Feedback::Correct => "result_correct",
+        Feedback::Present => "result_present",
+        Feedback::Absent => "result_absent",
+    })
+}
+
+// e.g. "letter T, correct position", or just "letter T" without a result
+fn letter_label(c: char, result: Option<Feedback>, lang: Locale) -> String {
+    let letter = c.to_ascii_uppercase();
+    match result {
+        Some(result) => lang.format(
+            "tile_result",
+            &[("letter", &letter), ("result", &describe(result, lang))],
+        ),
+        None => lang.format("tile_letter", &[("letter", &letter)]),
+    }
+}
+
+// The text announced to screen readers after an update: the result of a submitted
+// guess, why a guess was rejected and how the game ended. It is swapped into the
+// live region outside the board, which stays on the page so that changes are read out.
+pub fn announcement(before: &GameState, state: &GameState, lang: Locale) -> Markup {
+    let mut parts = Vec::new();
+    if state.error != Error::None {
+        parts.push(lang.text(state.error.key()).to_string());
+    }
+    if state.guesses.len() > before.guesses.len() {
+        let guess = *state.guesses.last().unwrap();
+        let letters = guess
+            .iter()
+            .zip(feedback::score(guess, state.answer))
+            .map(|(c, result)| {
+                lang.format(
+                    "announce_letter",
+                    &[
+                        ("letter", &c.to_ascii_uppercase()),
+                        ("result", &describe(result, lang)),
+                    ],
+                )
+            })
+            .collect::<Vec<_>>();
+        parts.push(format!("{}: {}", uppercase(guess), letters.join(", ")));
+    }
+    match state.phase {
+        Phase::Won => parts.push(lang.text("won").to_string()),
+        Phase::Lost => parts.push(format!(
+            "{} {}",
+            lang.text("lost"),
+            lang.format("answer_was", &[("answer", &uppercase(state.answer))])
+        )),
+        _ => {}
+    }
+    html! {
+        div #announce hx-swap-oob="innerHTML" { (parts.join(". ")) }
+    }
+}
+
+// The statistics dialog. `last` is the guess count of the game just won, which is highlighted.
+pub fn stats_modal(stats: &Stats, last: Option<usize>, lang: Locale) -> Markup {
+    let max = stats.distribution.iter().copied().max().unwrap_or(0).max(1);
+    html! {
+        dialog.stats open aria-labelledby="stats-title" {
+            h2 #stats-title { (lang.text("statistics")) }
+            div.numbers {
+                (stat(stats.played, lang.text("played")))
+                (stat(stats.win_percentage(), lang.text("win_percentage")))
+                (stat(stats.current_streak, lang.text("current_streak")))
+                (stat(stats.max_streak, lang.text("max_streak")))
+            }
+            h3 { (lang.text("guess_distribution")) }
+            div.distribution {
+                @for (i, count) in stats.distribution.iter().enumerate() {
+                    @let width = (*count as f64 / max as f64 * 100.0).max(7.0);
+                    div.row {
+                        span.guesses { (i + 1) }
+                        span.bar .highlight[last == Some(i + 1)] style=(format!("width: {:.0}%", width)) {
+                            (count)
+                        }
+                    }
+                }
+            }
+            form method="dialog" {
+                button { (lang.text("close")) }
+            }
+        }
+    }
+}
+
+fn stat(value: u32, label: &str) -> Markup {
+    html! {
+        div.stat {
+            div.value { (value) }
+            div.label { (label) }
+        }
+    }
+}
+
+// The share text in a read only text area, so it can be copied by hand if the clipboard is unavailable
+pub fn share(text: &str, lang: Locale) -> Markup {
+    html! {
+        div #share .panel {
+            textarea #share-text readonly rows=(text.lines().count()) aria-label=(lang.text("share_label")) { (text) }
+            div {
+                button.copy data-target="share-text" data-copied=(lang.text("copied")) { (lang.text("copy")) }
+            }
+        }
+    }
+}
+
+pub fn reset_button(mode: Mode, label: &str) -> Markup {
+    let vals = format!(r#"{{"mode": "{}"}}"#, mode.as_str());
+    html! {
+        button hx-post="/api/reset" hx-vals=(vals) hx-target="#game" hx-swap="outerHTML" { (label) }
+    }
+}
+
+pub fn settings(settings: &Settings, lang: Locale) -> Markup {
+    html! {
+        form #settings .panel hx-post="/api/settings" hx-trigger="change" hx-swap="outerHTML" {
+            label {
+                input type="checkbox" name="hard" value="true" checked[settings.hard];
+                " " (lang.text("hard_mode"))
+            }
+            label {
+                input type="checkbox" name="exclude_daily" value="true" checked[settings.exclude_daily];
+                " " (lang.text("skip_daily"))
+            }
+            label {
+                (lang.text("keyboard")) " "
+                select name="layout" {
+                    @for layout in Layout::ALL {
+                        option value=(layout.as_str()) selected[layout == settings.layout] { (layout.name()) }
+                    }
+                }
+            }
+            label {
+                (lang.text("theme")) " "
+                select name="theme" {
+                    @for theme in Theme::ALL {
+                        option value=(theme.as_str()) selected[theme == settings.theme] { (lang.text(theme.name())) }
+                    }
                 }
             }
+            label {
+                (lang.text("language")) " "
+                select name="locale" {
+                    option value="" selected[settings.locale.is_none()] { (lang.text("language_auto")) }
+                    @for locale in Locale::ALL {
+                        option value=(locale.as_str()) selected[settings.locale == Some(locale)] { (locale.name()) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn word_list(words: &[Word]) -> Markup {
+    html! {
+        ul.words {
+            @for word in words {
+                li.word { (word) }
+            }
         }
     }
 }
 
-pub fn guess_table(content: Markup) -> Markup {
+// Renders a table of counts, shading each cell by its value relative to the largest value
+pub fn heatmap(columns: &[String], rows: &[(String, Vec<usize>)]) -> Markup {
+    let max = rows
+        .iter()
+        .flat_map(|(_, values)| values.iter().copied())
+        .max()
+        .unwrap_or(0)
+        .max(1);
     html! {
-        table.game {
+        table.heatmap {
+            tr {
+                th {}
+                @for column in columns {
+                    th { (column) }
+                }
+            }
+            @for (label, values) in rows {
+                tr {
+                    th { (label) }
+                    @for value in values {
+                        @let alpha = *value as f64 / max as f64;
+                        td style=(format!("background-color: rgba(83, 141, 78, {:.2})", alpha)) { (value) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn letter_stats(stats: &LetterStats, lang: Locale) -> Markup {
+    let letters = (0..frequency::LETTERS)
+        .map(|i| frequency::letter(i).to_string())
+        .collect::<Vec<_>>();
+    let columns = [
+        lang.text("all"),
+        "1",
+        "2",
+        "3",
+        "4",
+        "5",
+        lang.text("words"),
+    ]
+    .map(String::from);
+    let rows = letters
+        .iter()
+        .enumerate()
+        .map(|(i, label)| {
+            let mut values = vec![stats.overall[i]];
+            values.extend(stats.positional.iter().map(|position| position[i]));
+            values.push(stats.coverage[i]);
+            (label.clone(), values)
+        })
+        .collect::<Vec<_>>();
+    let bigrams = letters
+        .iter()
+        .enumerate()
+        .map(|(i, label)| (label.clone(), stats.bigrams[i].to_vec()))
+        .collect::<Vec<_>>();
+    html! {
+        h2 { (lang.text("letters")) }
+        (heatmap(&columns, &rows))
+        h2 { (lang.text("bigrams")) }
+        (heatmap(&letters, &bigrams))
+    }
+}
+
+// Lists the letters that best split the remaining candidates
+pub fn informative_letters(stats: &LetterStats, lang: Locale) -> Markup {
+    html! {
+        div.letters {
+            @for (c, _) in stats.informative().iter().take(8) {
+                @let coverage = stats.coverage_score(*c) * 100.0;
+                @let percent = format!("{:.0}", coverage);
+                span.letter title=(lang.format("in_candidates", &[("percent", &percent)])) {
+                    span.word { (c) } " " (format!("{:.0}%", coverage))
+                }
+            }
+        }
+    }
+}
+
+pub fn guess_table(label: &str, content: Markup) -> Markup {
+    html! {
+        table.game aria-label=(label) {
             (content)
         }
     }
 }
 
-pub fn guess_row(guess: Word, correct: Word, required: Charset, fixed: bool) -> Markup {
+pub fn guess_row(
+    guess: Word,
+    correct: Word,
+    required: Charset,
+    fixed: bool,
+    lang: Locale,
+) -> Markup {
     html! {
         tr .guess {
             @for (i,c) in guess.into_iter().enumerate() {
                 @let exists = required.includes(c);
                 @let correct = c == correct.at(i) && c != ' ';
-                (guess_cell(c, fixed, exists, correct))
+                (guess_cell(c, fixed, exists, correct, lang))
             }
         }
     }
 }
 
-fn guess_cell(char: char, fixed: bool, exists: bool, correct: bool) -> Markup {
+// A submitted guess, colored by its feedback against the answer
+fn scored_row(guess: Word, answer: Word, lang: Locale) -> Markup {
     html! {
-        td .fixed[fixed] .exists[exists] .correct[correct] valign="middle" {
+        tr .guess {
+            @for (c, result) in guess.iter().zip(feedback::score(guess, answer)) {
+                (guess_cell(c, true, result == Feedback::Present, result == Feedback::Correct, lang))
+            }
+        }
+    }
+}
+
+fn guess_cell(char: char, fixed: bool, exists: bool, correct: bool, lang: Locale) -> Markup {
+    let result = if correct {
+        Some(Feedback::Correct)
+    } else if exists {
+        Some(Feedback::Present)
+    } else if fixed {
+        Some(Feedback::Absent)
+    } else {
+        None
+    };
+    let label = if char == ' ' {
+        lang.text("tile_empty").to_string()
+    } else {
+        letter_label(char, result, lang)
+    };
+    html! {
+        td .fixed[fixed] .exists[exists] .correct[correct] valign="middle" aria-label=(label) {
             (char)
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_keyboard_layouts() {
+        let state = GameState::new("crane");
+        // the letter keys in the order they are rendered
+        let keys = |layout: Layout| {
+            let markup = keyboard(&state, layout, Locale::En, false).into_string();
+            markup
+                .split(r#"{&quot;key&quot;: &quot;"#)
+                .skip(1)
+                .filter_map(|part| part.split_once("&quot;").map(|(key, _)| key.to_string()))
+                .filter(|key| key.len() == 1)
+                .collect::<String>()
+        };
+        assert_eq!(keys(Layout::Qwerty), "qwertyuiopasdfghjklzxcvbnm");
+        assert_eq!(keys(Layout::Azerty), "azertyuiopqsdfghjklmwxcvbn");
+        assert_eq!(keys(Layout::Qwertz), "qwertzuiopasdfghjklyxcvbnm");
+    }
+}
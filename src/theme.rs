@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+use crate::feedback::Feedback;
+
+// Color themes. The high contrast theme swaps green and yellow for orange and blue,
+// which stay distinguishable with the common kinds of color blindness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    Contrast,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Dark, Theme::Light, Theme::Contrast];
+
+    // Also the class set on the page body
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+            Theme::Contrast => "contrast",
+        }
+    }
+
+    // The key of the theme name in the message catalogs
+    pub fn name(&self) -> &'static str {
+        match self {
+            Theme::Dark => "theme_dark",
+            Theme::Light => "theme_light",
+            Theme::Contrast => "theme_contrast",
+        }
+    }
+
+    // The emoji tile for a letter result in shared games
+    pub fn tile(&self, result: Feedback) -> char {
+        match (result, self) {
+            (Feedback::Correct, Theme::Contrast) => '🟧',
+            (Feedback::Present, Theme::Contrast) => '🟦',
+            (Feedback::Correct, _) => '🟩',
+            (Feedback::Present, _) => '🟨',
+            (Feedback::Absent, Theme::Light) => '⬜',
+            (Feedback::Absent, _) => '⬛',
+        }
+    }
+}
@@ -59,6 +59,10 @@ impl Word {
         false
     }
 
+    pub fn count(&self, c: char) -> usize {
+        self.iter().filter(|x| *x == c).count()
+    }
+
     pub fn at(&self, i: usize) -> char {
         let offset = i * 5;
         let bits = (self.0 >> offset) & MASK;
@@ -229,6 +233,14 @@ mod test {
         assert_eq!(word.contains('d'), false);
     }
 
+    #[test]
+    fn test_word_count() {
+        let word = Word::new("hello");
+        assert_eq!(word.count('l'), 2);
+        assert_eq!(word.count('h'), 1);
+        assert_eq!(word.count('x'), 0);
+    }
+
     #[test]
     fn test_word_to_charset() {
         let word = Word::new("hello");